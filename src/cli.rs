@@ -0,0 +1,377 @@
+//! Subcommands for using the stopwatch data without a window.
+
+use std::time::SystemTime;
+
+use chrono::NaiveDate;
+
+use zarthus_stopwatch::config::{config_path, read_config, set_config_value};
+use zarthus_stopwatch::countdown::parse_duration;
+use zarthus_stopwatch::import::import_legacy_log;
+use zarthus_stopwatch::session::unix_secs;
+use zarthus_stopwatch::stats::{local_date, local_time};
+use zarthus_stopwatch::{
+    export, format_text, load_config, Config, Format, Session, SessionStore, Stats, Stopwatch,
+    Totals,
+};
+
+pub const USAGE: &str = "\
+Usage: zarthus_stopwatch [OPTIONS] [COMMAND]
+
+Without a command the stopwatch window is opened.
+
+Options:
+  --countdown <DURATION>       Start counting down from DURATION, like 25, 1h30m or 10:00
+  --config <PATH>              Use PATH as the config file
+  --data-dir <PATH>            Keep the session history in PATH
+  --profile <NAME>             Use the thresholds and history of profile NAME for this run
+
+Environment:
+  STOPWATCH_HOME               Directory to keep both the config and the history in
+
+Commands:
+  status                       Show whether the timer is running and for how long
+  report [--since YYYY-MM-DD]  Show totals per day and week
+  export [--format FORMAT]     Write the session history to stdout as csv, json or ics
+  import <PATH>                Add the sessions of an old zarthus_counter.log to the history
+  config show                  Print the current config
+  config set <KEY> <VALUE>     Change a single config value
+  help                         Show this message";
+
+#[derive(Debug)]
+pub enum Command {
+    Status,
+    Report { since: Option<NaiveDate> },
+    Export { format: Format },
+    Import { path: std::path::PathBuf },
+    ConfigShow,
+    ConfigSet { key: String, value: String },
+    Help,
+}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    /// `None` means the GUI should be started.
+    pub command: Option<Command>,
+    /// Seconds to count down from in the GUI.
+    pub countdown: Option<u64>,
+    pub config: Option<std::path::PathBuf>,
+    pub data_dir: Option<std::path::PathBuf>,
+    /// Overrides the profile selected in the config.
+    pub profile: Option<String>,
+}
+
+/// Parses the arguments after the program name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.into_iter();
+    let mut parsed = Args::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--countdown" => {
+                parsed.countdown = Some(parse_duration(&flag_value(&arg, args.next())?)?);
+            }
+            "--config" => parsed.config = Some(flag_value(&arg, args.next())?.into()),
+            "--data-dir" => parsed.data_dir = Some(flag_value(&arg, args.next())?.into()),
+            "--profile" => parsed.profile = Some(flag_value(&arg, args.next())?),
+            _ => {
+                parsed.command = Some(parse_command(&arg, args)?);
+                break;
+            }
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_command(command: &str, mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let command = match command {
+        "status" => Command::Status,
+        "report" => {
+            let mut since = None;
+            while let Some(flag) = args.next() {
+                match flag.as_str() {
+                    "--since" => {
+                        let date = flag_value(&flag, args.next())?;
+                        since = Some(
+                            NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                                .map_err(|e| format!("Invalid date {}: {}", date, e))?,
+                        );
+                    }
+                    _ => return Err(format!("Unknown argument: {}", flag)),
+                }
+            }
+            Command::Report { since }
+        }
+        "export" => {
+            let mut format = Format::Csv;
+            while let Some(flag) = args.next() {
+                match flag.as_str() {
+                    "--format" => format = flag_value(&flag, args.next())?.parse()?,
+                    _ => return Err(format!("Unknown argument: {}", flag)),
+                }
+            }
+            Command::Export { format }
+        }
+        "import" => Command::Import {
+            path: args.next().ok_or("Missing path to import")?.into(),
+        },
+        "config" => match args.next().as_deref() {
+            Some("show") => Command::ConfigShow,
+            Some("set") => Command::ConfigSet {
+                key: args.next().ok_or("Missing config key")?,
+                value: args.next().ok_or("Missing config value")?,
+            },
+            Some(other) => return Err(format!("Unknown config command: {}", other)),
+            None => return Err("Missing config command".to_owned()),
+        },
+        "help" | "--help" | "-h" => Command::Help,
+        other => return Err(format!("Unknown command: {}", other)),
+    };
+
+    if let Some(extra) = args.next() {
+        return Err(format!("Unexpected argument: {}", extra));
+    }
+
+    Ok(command)
+}
+
+fn flag_value(flag: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("Missing value for {}", flag))
+}
+
+/// Runs `command`, using the history and thresholds of `profile` if given.
+pub fn run(command: Command, profile: Option<&str>) -> Result<(), String> {
+    match command {
+        Command::Status => status(&config(profile)?),
+        Command::Report { since } => report(&config(profile)?, since),
+        Command::Export { format } => {
+            let sessions = history(&config(profile)?)?;
+            let mut stdout = std::io::stdout().lock();
+            export::export(&sessions, format, &mut stdout)
+                .map_err(|e| format!("Failed to export: {}", e))
+        }
+        Command::Import { path } => {
+            let store = SessionStore::open_default(config(profile)?.profile_name())
+                .ok_or("No data directory to import into")?;
+            let count = import_legacy_log(&path, &store)?;
+            println!(
+                "Imported {} sessions into {}",
+                count,
+                store.path().display()
+            );
+            Ok(())
+        }
+        Command::ConfigShow => {
+            let (config, warnings) = read_config().map_err(|e| e.to_string())?;
+            for warning in warnings {
+                eprintln!("{}", warning);
+            }
+            let toml = toml::to_string_pretty(&config).map_err(|e| e.to_string())?;
+            println!("# {}", config_path().map_err(|e| e.to_string())?.display());
+            print!("{}", toml);
+            Ok(())
+        }
+        Command::ConfigSet { key, value } => {
+            set_config_value(&key, &value).map_err(|e| e.to_string())?;
+            println!("{} updated", key);
+            Ok(())
+        }
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
+
+/// The config with `profile` selected, or the defaults after warning about why it could not be
+/// read.
+fn config(profile: Option<&str>) -> Result<Config, String> {
+    let (mut config, errors) = load_config();
+    for e in errors {
+        if e.is_warning() {
+            eprintln!("{}", e);
+        } else {
+            eprintln!("{}, using the default config", e);
+        }
+    }
+
+    if let Some(profile) = profile {
+        config.select_profile(profile)?;
+    }
+
+    Ok(config)
+}
+
+fn history(config: &Config) -> Result<Vec<Session>, String> {
+    SessionStore::open_default(config.profile_name())
+        .ok_or("No data directory to read sessions from")?
+        .load()
+}
+
+fn status(config: &Config) -> Result<(), String> {
+    let mut sessions = history(config)?;
+
+    match Stopwatch::resume(&sessions) {
+        Some(stopwatch) => {
+            println!(
+                "{} {}{}",
+                if stopwatch.is_paused() {
+                    "paused"
+                } else {
+                    "active"
+                },
+                format_text(stopwatch.elapsed(), true),
+                stopwatch
+                    .label()
+                    .map(|label| format!(" ({})", label))
+                    .unwrap_or_default()
+            );
+            sessions.push(stopwatch.current());
+        }
+        None => println!("no sessions recorded"),
+    }
+
+    let today = local_date(unix_secs(SystemTime::now()));
+    sessions.retain(|s| local_date(s.start) == today);
+    let stats = Stats::compute(&sessions, &config.warn_settings());
+    print_totals("today", &stats.total);
+
+    Ok(())
+}
+
+fn report(config: &Config, since: Option<NaiveDate>) -> Result<(), String> {
+    let mut sessions = history(config)?;
+    if let Some(since) = since {
+        sessions.retain(|s| local_date(s.start).is_some_and(|day| day >= since));
+    }
+
+    let stats = Stats::compute(&sessions, &config.warn_settings());
+    if let (Some(first), Some(last)) = (sessions.first(), sessions.last()) {
+        if let (Some(from), Some(to)) = (local_time(first.start), local_time(last.end)) {
+            println!(
+                "{} - {}",
+                from.format("%Y-%m-%d %H:%M"),
+                to.format("%Y-%m-%d %H:%M")
+            );
+        }
+    }
+
+    for (day, totals) in &stats.days {
+        print_totals(&day.format("%a %Y-%m-%d").to_string(), totals);
+    }
+    for (monday, totals) in &stats.weeks {
+        print_totals(&monday.format("week %G-W%V").to_string(), totals);
+    }
+    for (label, totals) in &stats.labels {
+        print_totals(label, totals);
+    }
+    print_totals("total", &stats.total);
+
+    Ok(())
+}
+
+fn print_totals(title: &str, totals: &Totals) {
+    println!(
+        "{:<16} active {}  breaks {} ({})  longest {}  over limit {}  snoozed {}",
+        title,
+        format_text(totals.active, true),
+        format_text(totals.pause, true),
+        totals.breaks,
+        format_text(totals.longest_active, true),
+        totals.danger_exceeded,
+        totals.snoozes,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_owned).collect()
+    }
+
+    fn command(line: &str) -> Result<Option<Command>, String> {
+        parse(args(line)).map(|parsed| parsed.command)
+    }
+
+    #[test]
+    fn parses_commands_and_their_flags() {
+        let since = NaiveDate::from_ymd_opt(2026, 10, 1);
+
+        assert!(matches!(command(""), Ok(None)));
+        assert!(matches!(command("status"), Ok(Some(Command::Status))));
+        assert!(matches!(
+            command("report"),
+            Ok(Some(Command::Report { since: None }))
+        ));
+        assert!(matches!(
+            command("report --since 2026-10-01"),
+            Ok(Some(Command::Report { since: date })) if date == since
+        ));
+        assert!(matches!(
+            command("export --format CSV"),
+            Ok(Some(Command::Export {
+                format: Format::Csv
+            }))
+        ));
+        match command("config set warn_after_minutes 50") {
+            Ok(Some(Command::ConfigSet { key, value })) => {
+                assert_eq!((key.as_str(), value.as_str()), ("warn_after_minutes", "50"));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        for (line, error) in [
+            ("report --since", "Missing value for --since"),
+            ("export --format", "Missing value for --format"),
+            ("report --until 2026-10-01", "Unknown argument: --until"),
+            ("export --format xml", "Unknown export format: xml"),
+            ("status now", "Unexpected argument: now"),
+            ("config show all", "Unexpected argument: all"),
+            ("config set warn_after_minutes", "Missing config value"),
+            ("stats", "Unknown command: stats"),
+        ] {
+            assert_eq!(command(line).unwrap_err(), error, "{}", line);
+        }
+    }
+
+    #[test]
+    fn rejects_an_invalid_since_date() {
+        for date in ["2026-13-01", "01-10-2026", "yesterday"] {
+            let e = command(&format!("report --since {}", date)).unwrap_err();
+
+            assert!(e.starts_with(&format!("Invalid date {}: ", date)), "{}", e);
+        }
+    }
+
+    #[test]
+    fn options_come_before_the_command() {
+        let line = "--countdown 25 --config a.toml --data-dir data status";
+        let parsed = parse(args(line)).unwrap();
+        assert_eq!(parsed.countdown, Some(25 * 60));
+        assert_eq!(parsed.config, Some(PathBuf::from("a.toml")));
+        assert_eq!(parsed.data_dir, Some(PathBuf::from("data")));
+        assert!(matches!(parsed.command, Some(Command::Status)));
+
+        let parsed = parse(args("--profile meetings")).unwrap();
+        assert_eq!(parsed.profile.as_deref(), Some("meetings"));
+        assert!(parsed.command.is_none());
+
+        for (line, error) in [
+            ("status --countdown 25", "Unexpected argument: --countdown"),
+            ("status --profile x", "Unexpected argument: --profile"),
+            ("--countdown", "Missing value for --countdown"),
+            ("--countdown soon", "Invalid duration: soon"),
+            ("--data-dir", "Missing value for --data-dir"),
+            ("--verbose status", "Unknown command: --verbose"),
+        ] {
+            assert_eq!(parse(args(line)).unwrap_err(), error, "{}", line);
+        }
+    }
+}
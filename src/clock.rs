@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant, SystemTime};
+
+/// Gaps between ticks shorter than this are taken for a busy or throttled app, not for a
+/// suspend.
+pub const MIN_GAP: Duration = Duration::from_secs(60);
+
+/// Compares the wall clock against a monotonic one between ticks, to notice when the system
+/// was suspended or the wall clock was changed.
+///
+/// Sessions are stored in wall clock time, so neither can be avoided, only noticed and dealt
+/// with. The monotonic clock stops during a suspend on Linux and macOS, while on Windows it
+/// keeps going but the ticks stop, both of which show as a gap.
+#[derive(Debug, Clone, Copy)]
+pub struct ClockWatch {
+    instant: Instant,
+    wall: SystemTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockJump {
+    /// No ticks between `start` and `end`, usually because the system was suspended. A wall
+    /// clock set forward looks the same.
+    Gap { start: SystemTime, end: SystemTime },
+    /// The wall clock was set back by this much.
+    Backwards(Duration),
+}
+
+impl Default for ClockWatch {
+    fn default() -> Self {
+        Self::new_at(Instant::now(), SystemTime::now())
+    }
+}
+
+impl ClockWatch {
+    pub fn new_at(instant: Instant, wall: SystemTime) -> Self {
+        Self { instant, wall }
+    }
+
+    pub fn tick(&mut self) -> Option<ClockJump> {
+        self.tick_at(Instant::now(), SystemTime::now())
+    }
+
+    /// Compares both clocks with the previous tick, returning what happened in between if it
+    /// was more than time passing normally.
+    pub fn tick_at(&mut self, instant: Instant, wall: SystemTime) -> Option<ClockJump> {
+        let last = std::mem::replace(self, Self::new_at(instant, wall));
+        let monotonic = instant.saturating_duration_since(last.instant);
+        let expected = last.wall + monotonic;
+
+        match wall.duration_since(expected) {
+            Ok(ahead) if ahead >= MIN_GAP || monotonic >= MIN_GAP => Some(ClockJump::Gap {
+                start: last.wall,
+                end: wall,
+            }),
+            Ok(_) => None,
+            Err(e) if e.duration() >= Duration::from_secs(1) => {
+                Some(ClockJump::Backwards(e.duration()))
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn watch() -> (ClockWatch, Instant, SystemTime) {
+        let instant = Instant::now();
+        let wall = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        (ClockWatch::new_at(instant, wall), instant, wall)
+    }
+
+    #[test]
+    fn time_passing_normally_is_no_jump() {
+        let (mut clock, instant, wall) = watch();
+
+        let second = Duration::from_secs(1);
+        assert_eq!(clock.tick_at(instant + second, wall + second), None);
+        assert_eq!(
+            clock.tick_at(instant + 2 * second, wall + 2 * second - second / 2),
+            None
+        );
+    }
+
+    #[test]
+    fn suspend_with_a_stopped_monotonic_clock_is_a_gap() {
+        let (mut clock, instant, wall) = watch();
+
+        let jump = clock.tick_at(instant + Duration::from_secs(1), wall + 2 * HOUR);
+
+        assert_eq!(
+            jump,
+            Some(ClockJump::Gap {
+                start: wall,
+                end: wall + 2 * HOUR
+            })
+        );
+    }
+
+    #[test]
+    fn late_tick_is_a_gap() {
+        let (mut clock, instant, wall) = watch();
+
+        let jump = clock.tick_at(instant + 2 * HOUR, wall + 2 * HOUR);
+
+        assert_eq!(
+            jump,
+            Some(ClockJump::Gap {
+                start: wall,
+                end: wall + 2 * HOUR
+            })
+        );
+    }
+
+    #[test]
+    fn clock_set_back_is_reported_by_how_much() {
+        let (mut clock, instant, wall) = watch();
+        let second = Duration::from_secs(1);
+
+        let jump = clock.tick_at(instant + second, wall + second - HOUR);
+
+        assert_eq!(jump, Some(ClockJump::Backwards(HOUR)));
+        assert_eq!(
+            clock.tick_at(instant + 2 * second, wall + 2 * second - HOUR),
+            None
+        );
+    }
+}
@@ -0,0 +1,817 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use toml_edit::{DocumentMut, Item};
+
+use crate::keys::{Action, KeyBinding, Modifiers};
+use crate::{Level, WarnSettings};
+
+/// Version of the config layout, bumped whenever keys are added; see [`migrate`].
+pub const CONFIG_VERSION: u32 = 7;
+
+/// Beyond this many pixels from the origin a window is assumed to be off-screen.
+const MAX_DESKTOP_EXTENT: f32 = 16384.;
+
+/// Missing keys take their value from [`Config::default`], so older files keep loading.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub config_version: u32,
+    pub warn_after_minutes: u16,
+    pub danger_after_minutes: u16,
+    /// Updated from the window when it is closed.
+    pub window_size: [f32; 2],
+    pub window_position: [f32; 2],
+    pub always_on_top: bool,
+    /// Closing the window hides it in the tray instead of quitting.
+    /// Only supported on Linux if feature tray enabled
+    pub minimize_to_tray: bool,
+    pub theme: ColorScheme,
+    pub start_unpaused: bool,
+    /// Resume the last run from the session history on startup.
+    /// Only supported if feature store_sessions enabled
+    pub store_last_session: bool,
+    /// Only supported if feature notifications enabled
+    pub notify_on_warn: bool,
+    pub notify_on_danger: bool,
+    /// Repeat the danger notification this often, 0 to only send it once
+    pub remind_every_minutes: u16,
+    /// Pause after this long without input, counting the time away as a break. 0 to never pause.
+    /// Only supported if feature idle enabled
+    pub pause_when_idle_minutes: u16,
+    /// `[work, break]` minutes to switch between automatically, empty to count up freely
+    pub intervals: Vec<[u16; 2]>,
+    /// Further stopwatches shown below the main one
+    pub stopwatches: Vec<StopwatchConfig>,
+    pub keys: Keys,
+    /// Name of the profile in use, empty for none
+    pub profile: String,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Key bindings like `space` or `ctrl+s`, empty to leave an action unbound.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Keys {
+    pub toggle: String,
+    pub reset: String,
+    pub undo: String,
+    pub stats: String,
+    pub label: String,
+    /// Toggles even while the window is not focused.
+    /// Only supported if feature global_hotkeys enabled
+    pub global_toggle: String,
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Self {
+            toggle: "space".to_owned(),
+            reset: "r".to_owned(),
+            undo: "u".to_owned(),
+            stats: "s".to_owned(),
+            label: "l".to_owned(),
+            global_toggle: String::new(),
+        }
+    }
+}
+
+impl Keys {
+    fn binding(&self, action: Action) -> &str {
+        match action {
+            Action::Toggle => &self.toggle,
+            Action::Reset => &self.reset,
+            Action::Undo => &self.undo,
+            Action::Stats => &self.stats,
+            Action::Label => &self.label,
+        }
+    }
+
+    /// The in-window bindings that are set.
+    pub fn bindings(&self) -> Result<Vec<(Action, KeyBinding)>, String> {
+        Action::ALL
+            .into_iter()
+            .filter(|action| !self.binding(*action).is_empty())
+            .map(|action| Ok((action, self.binding(action).parse()?)))
+            .collect()
+    }
+
+    /// The bindings that should work while the window is not focused.
+    pub fn global_bindings(&self) -> Result<Vec<(Action, KeyBinding)>, String> {
+        if self.global_toggle.is_empty() {
+            return Ok(vec![]);
+        }
+
+        Ok(vec![(Action::Toggle, self.global_toggle.parse()?)])
+    }
+
+    /// The action bound to `key` with `modifiers` held, bindings that don't parse being skipped.
+    pub fn action_for(&self, key: &str, modifiers: Modifiers) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| {
+            self.binding(*action)
+                .parse::<KeyBinding>()
+                .is_ok_and(|binding| binding.matches(key, modifiers))
+        })
+    }
+}
+
+/// Thresholds and window geometry for one kind of work, with its own session history.
+///
+/// Anything left out falls back to the top-level value.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warn_after_minutes: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub danger_after_minutes: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_size: Option<[f32; 2]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_position: Option<[f32; 2]>,
+}
+
+/// An additional stopwatch with its own session history.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct StopwatchConfig {
+    pub name: String,
+    /// Defaults to the main `warn_after_minutes`, or that of the profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warn_after_minutes: Option<u16>,
+    /// Defaults to the main `danger_after_minutes`, or that of the profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub danger_after_minutes: Option<u16>,
+}
+
+/// Base colours of the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    Dark,
+    Light,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            config_version: CONFIG_VERSION,
+            warn_after_minutes: 45,
+            danger_after_minutes: 60,
+            window_size: [180., 80.],
+            window_position: [40., 40.],
+            always_on_top: false,
+            minimize_to_tray: false,
+            theme: ColorScheme::Dark,
+            start_unpaused: false,
+            store_last_session: true,
+            notify_on_warn: true,
+            notify_on_danger: true,
+            remind_every_minutes: 10,
+            pause_when_idle_minutes: 0,
+            intervals: vec![],
+            stopwatches: vec![],
+            keys: Keys::default(),
+            profile: String::new(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// The profile named by `profile`, `None` if it is empty or there is no such profile.
+    pub fn active_profile(&self) -> Option<&Profile> {
+        self.profiles.get(&self.profile)
+    }
+
+    /// Switches to the profile called `name`, an empty name going back to the top-level settings.
+    pub fn select_profile(&mut self, name: &str) -> Result<(), String> {
+        if !name.is_empty() && !self.profiles.contains_key(name) {
+            return Err(format!("Unknown profile: {}", name));
+        }
+
+        self.profile = name.to_owned();
+        Ok(())
+    }
+
+    /// Name of the active profile, for keeping its sessions apart.
+    pub fn profile_name(&self) -> Option<&str> {
+        self.active_profile().map(|_| self.profile.as_str())
+    }
+
+    /// Warn and danger minutes, those of the active profile taking precedence.
+    pub fn thresholds(&self) -> (u16, u16) {
+        let profile = self.active_profile();
+
+        (
+            profile
+                .and_then(|p| p.warn_after_minutes)
+                .unwrap_or(self.warn_after_minutes),
+            profile
+                .and_then(|p| p.danger_after_minutes)
+                .unwrap_or(self.danger_after_minutes),
+        )
+    }
+
+    /// Sets the thresholds of the active profile, or the top-level ones without a profile.
+    pub fn set_thresholds(&mut self, warn_after_minutes: u16, danger_after_minutes: u16) {
+        match self.profiles.get_mut(&self.profile) {
+            Some(profile) => {
+                profile.warn_after_minutes = Some(warn_after_minutes);
+                profile.danger_after_minutes = Some(danger_after_minutes);
+            }
+            None => {
+                self.warn_after_minutes = warn_after_minutes;
+                self.danger_after_minutes = danger_after_minutes;
+            }
+        }
+    }
+
+    pub fn warn_settings(&self) -> WarnSettings {
+        let (warn, danger) = self.thresholds();
+
+        WarnSettings::from_minutes(warn, danger)
+    }
+
+    /// Thresholds of an additional stopwatch, falling back to the main ones.
+    pub fn warn_settings_for(&self, stopwatch: &StopwatchConfig) -> WarnSettings {
+        let (warn, danger) = self.thresholds();
+
+        WarnSettings::from_minutes(
+            stopwatch.warn_after_minutes.unwrap_or(warn),
+            stopwatch.danger_after_minutes.unwrap_or(danger),
+        )
+    }
+
+    /// Window size and position, those of the active profile taking precedence.
+    pub fn window_geometry(&self) -> ([f32; 2], [f32; 2]) {
+        let profile = self.active_profile();
+
+        (
+            profile
+                .and_then(|p| p.window_size)
+                .unwrap_or(self.window_size),
+            profile
+                .and_then(|p| p.window_position)
+                .unwrap_or(self.window_position),
+        )
+    }
+
+    /// Sets the window geometry of the active profile, or the top-level one without a profile.
+    pub fn set_window_geometry(&mut self, size: [f32; 2], position: [f32; 2]) {
+        match self.profiles.get_mut(&self.profile) {
+            Some(profile) => {
+                profile.window_size = Some(size);
+                profile.window_position = Some(position);
+            }
+            None => {
+                self.window_size = size;
+                self.window_position = position;
+            }
+        }
+    }
+
+    /// The saved window position, unless it can't be on any screen.
+    ///
+    /// Without knowing the monitors only positions that are off-screen anywhere are caught: the
+    /// window entirely left of or above the origin, or further out than any desktop reaches.
+    /// Minimized windows on Windows report such positions as well.
+    pub fn window_position_on_screen(&self) -> Option<[f32; 2]> {
+        let ([width, height], position) = self.window_geometry();
+        let [x, y] = position;
+        let on_screen = x.is_finite()
+            && y.is_finite()
+            && x + width > 0.
+            && y + height > 0.
+            && x < MAX_DESKTOP_EXTENT
+            && y < MAX_DESKTOP_EXTENT;
+
+        on_screen.then_some(position)
+    }
+
+    /// The saved window position if the middle of the window is on a monitor of size `monitor` at
+    /// the origin, like the primary one usually is.
+    ///
+    /// A monitor that was unplugged or shrunk since leaves the window somewhere this catches.
+    pub fn window_position_on_monitor(&self, monitor: [f32; 2]) -> Option<[f32; 2]> {
+        let ([width, height], _) = self.window_geometry();
+        let position = self.window_position_on_screen()?;
+        let [x, y] = [position[0] + width / 2., position[1] + height / 2.];
+        let on_monitor = x >= 0. && y >= 0. && x < monitor[0] && y < monitor[1];
+
+        on_monitor.then_some(position)
+    }
+
+    /// Checks the values that parse fine but make no sense together.
+    pub fn validate(&self) -> Result<(), String> {
+        let (warn, danger) = self.thresholds();
+        if danger < warn {
+            return Err(format!(
+                "danger_after_minutes ({}) is below warn_after_minutes ({})",
+                danger, warn
+            ));
+        }
+
+        self.keys.bindings()?;
+        self.keys.global_bindings()?;
+
+        if !self.profile.is_empty() && self.active_profile().is_none() {
+            return Err(format!("Unknown profile: {}", self.profile));
+        }
+
+        Ok(())
+    }
+
+    /// Whether reaching `level` in an active session should send a notification.
+    pub fn notifies_on(&self, level: Level) -> bool {
+        match level {
+            Level::Warn => self.notify_on_warn,
+            Level::Danger => self.notify_on_danger,
+            Level::Off | Level::Ok => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The platform has no config directory.
+    NoConfigDir,
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The file is not valid TOML or does not fit [`Config`].
+    Invalid {
+        path: PathBuf,
+        /// The key that could not be read, if it could be pinned down.
+        key: Option<String>,
+        message: String,
+    },
+    UnknownKey(String),
+    Serialize(toml::ser::Error),
+    /// The config in use stands in for a file that failed to load, which saving would overwrite.
+    Fallback,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoConfigDir => write!(f, "No config directory found"),
+            Self::Io { path, source } => {
+                write!(f, "Failed to access {}: {}", path.display(), source)
+            }
+            Self::Invalid {
+                path,
+                key: Some(key),
+                message,
+            } => write!(f, "Invalid `{}` in {}: {}", key, path.display(), message),
+            Self::Invalid {
+                path,
+                key: None,
+                message,
+            } => write!(f, "Failed to parse {}: {}", path.display(), message),
+            Self::UnknownKey(key) => write!(f, "Unknown config key: {}", key),
+            Self::Serialize(e) => write!(f, "Failed to serialize config: {}", e),
+            Self::Fallback => write!(f, "Not saving over the config file until it loads"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Serialize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl ConfigError {
+    /// Whether the config was loaded anyway, only ignoring part of the file.
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Self::UnknownKey(_))
+    }
+
+    fn io(path: &Path, source: std::io::Error) -> Self {
+        Self::Io {
+            path: path.to_owned(),
+            source,
+        }
+    }
+
+    fn invalid(path: &Path, source: &str, message: &str, span: Option<Range<usize>>) -> Self {
+        Self::Invalid {
+            path: path.to_owned(),
+            key: invalid_key(source, message, span),
+            message: message.to_owned(),
+        }
+    }
+}
+
+/// Finds the key an error is about, from its message or the line it points at.
+fn invalid_key(source: &str, message: &str, span: Option<Range<usize>>) -> Option<String> {
+    if let Some(rest) = message.strip_prefix("missing field `") {
+        return rest.split('`').next().map(str::to_owned);
+    }
+
+    let span = span?;
+    let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+    let line = source[line_start..].lines().next()?;
+    let (key, _) = line.split_once('=')?;
+
+    Some(key.trim().to_owned())
+}
+
+/// See [`crate::paths::config_file`].
+pub fn config_path() -> Result<PathBuf, ConfigError> {
+    crate::paths::config_file().ok_or(ConfigError::NoConfigDir)
+}
+
+/// When the config file was last changed, `None` if it can't be read.
+pub fn config_modified() -> Option<SystemTime> {
+    std::fs::metadata(config_path().ok()?)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Reads the config, writing the defaults first if there is no config file yet.
+///
+/// A file from an older version is upgraded in place. Unknown keys are ignored and returned next
+/// to the config as warnings.
+pub fn read_config() -> Result<(Config, Vec<ConfigError>), ConfigError> {
+    let config_path = config_path()?;
+
+    if !config_path.exists() {
+        let config = Config::default();
+        write_config(&config_path, &config)?;
+
+        return Ok((config, vec![]));
+    }
+
+    let buf =
+        std::fs::read_to_string(&config_path).map_err(|e| ConfigError::io(&config_path, e))?;
+    let mut doc = parse_document(&config_path, &buf)?;
+    let mut config: Config = toml::from_str(&buf)
+        .map_err(|e| ConfigError::invalid(&config_path, &buf, e.message(), e.span()))?;
+
+    let defaults = default_document();
+    let warnings = doc
+        .iter()
+        .filter(|(key, _)| !defaults.contains_key(key))
+        .map(|(key, _)| ConfigError::UnknownKey(key.to_owned()))
+        .collect();
+
+    if migrate(&mut doc) {
+        match std::fs::write(&config_path, doc.to_string()) {
+            // Otherwise the next save would write the old version back.
+            Ok(()) => config.config_version = CONFIG_VERSION,
+            Err(e) => eprintln!("Failed to upgrade {}: {}", config_path.display(), e),
+        }
+    }
+
+    Ok((config, warnings))
+}
+
+/// Reads the config, falling back to the defaults if that fails.
+///
+/// A file that does not parse is copied to `zarthus_counter.toml.bak`, so it survives the app
+/// saving over it. The error is returned like the warnings, so it can be shown to the user.
+pub fn load_config() -> (Config, Vec<ConfigError>) {
+    match read_config() {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            if let ConfigError::Invalid { path, .. } = &e {
+                let backup = path.with_extension("toml.bak");
+                if let Err(e) = std::fs::copy(path, &backup) {
+                    eprintln!("Failed to back up config to {}: {}", backup.display(), e);
+                }
+            }
+
+            (Config::default(), vec![e])
+        }
+    }
+}
+
+fn write_config(path: &Path, config: &Config) -> Result<(), ConfigError> {
+    let toml = toml::to_string_pretty(config).map_err(ConfigError::Serialize)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| ConfigError::io(dir, e))?;
+    }
+
+    std::fs::write(path, toml).map_err(|e| ConfigError::io(path, e))
+}
+
+fn parse_document(path: &Path, source: &str) -> Result<DocumentMut, ConfigError> {
+    source
+        .parse::<DocumentMut>()
+        .map_err(|e| ConfigError::invalid(path, source, e.message(), e.span()))
+}
+
+/// [`Config::default`] as a document, listing every known key.
+fn default_document() -> DocumentMut {
+    toml::to_string_pretty(&Config::default())
+        .expect("the default config serializes")
+        .parse()
+        .expect("the default config is valid TOML")
+}
+
+/// Upgrades a document written by an older version, returning whether anything changed.
+///
+/// Keys added since are appended with their default values, everything the user wrote, comments
+/// included, stays where it was. Files without a `config_version` are version 1.
+fn migrate(doc: &mut DocumentMut) -> bool {
+    let version = doc
+        .get("config_version")
+        .and_then(Item::as_integer)
+        .unwrap_or(1);
+    if version >= CONFIG_VERSION as i64 {
+        return false;
+    }
+
+    for (key, item) in default_document().iter() {
+        if !doc.contains_key(key) {
+            doc.insert(key, item.clone());
+        }
+    }
+    doc["config_version"] = toml_edit::value(CONFIG_VERSION as i64);
+
+    true
+}
+
+/// Sets a single top-level key in the config file, keeping comments and every other line as is.
+///
+/// `value` is read as a TOML value, falling back to a plain string. The result must still be a
+/// valid [`Config`], which is returned.
+pub fn set_config_value(key: &str, value: &str) -> Result<Config, ConfigError> {
+    read_config()?;
+
+    if !default_document().contains_key(key) {
+        return Err(ConfigError::UnknownKey(key.to_owned()));
+    }
+
+    let config_path = config_path()?;
+    let buf =
+        std::fs::read_to_string(&config_path).map_err(|e| ConfigError::io(&config_path, e))?;
+    let mut doc = parse_document(&config_path, &buf)?;
+
+    let value = value
+        .parse::<toml_edit::Value>()
+        .unwrap_or_else(|_| value.into());
+    replace_item(&mut doc[key], Item::Value(value));
+
+    let toml = doc.to_string();
+    let config = toml::from_str::<Config>(&toml)
+        .map_err(|e| ConfigError::invalid(&config_path, &toml, e.message(), e.span()))?;
+    config.validate().map_err(|message| ConfigError::Invalid {
+        path: config_path.clone(),
+        key: Some(key.to_owned()),
+        message,
+    })?;
+
+    std::fs::write(&config_path, toml).map_err(|e| ConfigError::io(&config_path, e))?;
+
+    Ok(config)
+}
+
+/// Writes `config` to the config file, keeping comments and the order of the keys already in it.
+pub fn save_config(config: &Config) -> Result<(), ConfigError> {
+    let config_path = config_path()?;
+    let mut doc = read_document(&config_path)?;
+
+    for (key, item) in serialize_document(config)?.iter() {
+        replace_item(&mut doc[key], item.clone());
+    }
+
+    std::fs::write(&config_path, doc.to_string()).map_err(|e| ConfigError::io(&config_path, e))
+}
+
+/// Writes only the window geometry of `config` to the config file, the active profile's if there
+/// is one, like [`Config::set_window_geometry`].
+pub fn save_window_geometry(config: &Config) -> Result<(), ConfigError> {
+    let config_path = config_path()?;
+    let mut doc = read_document(&config_path)?;
+    set_window_geometry(&mut doc, config)?;
+
+    std::fs::write(&config_path, doc.to_string()).map_err(|e| ConfigError::io(&config_path, e))
+}
+
+fn set_window_geometry(doc: &mut DocumentMut, config: &Config) -> Result<(), ConfigError> {
+    let saved = serialize_document(config)?;
+
+    for key in ["window_size", "window_position"] {
+        match config.profile_name() {
+            Some(name) => {
+                let item = saved["profiles"][name].get(key).cloned();
+                replace_item(&mut doc["profiles"][name][key], item.unwrap_or_default());
+            }
+            None => replace_item(&mut doc[key], saved[key].clone()),
+        }
+    }
+
+    Ok(())
+}
+
+/// The config file as a document, an empty one if there is no file yet.
+fn read_document(path: &Path) -> Result<DocumentMut, ConfigError> {
+    match std::fs::read_to_string(path) {
+        Ok(buf) => parse_document(path, &buf),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(DocumentMut::new()),
+        Err(e) => Err(ConfigError::io(path, e)),
+    }
+}
+
+fn serialize_document(config: &Config) -> Result<DocumentMut, ConfigError> {
+    let toml = toml::to_string_pretty(config).map_err(ConfigError::Serialize)?;
+
+    Ok(toml
+        .parse::<DocumentMut>()
+        .expect("a serialized config is valid TOML"))
+}
+
+/// Replaces an item in place, a plain value keeping the comments around the old one.
+fn replace_item(old: &mut Item, mut item: Item) {
+    if let (Some(value), Some(old)) = (item.as_value_mut(), old.as_value()) {
+        *value.decor_mut() = old.decor().clone();
+    }
+
+    *old = item;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_profiles() -> Config {
+        toml::from_str(
+            "warn_after_minutes = 30\n\
+             danger_after_minutes = 50\n\
+             window_size = [200.0, 100.0]\n\
+             window_position = [10.0, 20.0]\n\
+             [profiles.deep_work]\n\
+             warn_after_minutes = 80\n\
+             window_position = [500.0, 20.0]\n\
+             [profiles.meetings]\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn migrate_appends_missing_keys_and_keeps_the_rest() {
+        let mut doc: DocumentMut = "# mine\nwarn_after_minutes = 30 # short\n".parse().unwrap();
+
+        assert!(migrate(&mut doc));
+        assert!(doc
+            .to_string()
+            .contains("# mine\nwarn_after_minutes = 30 # short\n"));
+        assert_eq!(
+            doc["config_version"].as_integer(),
+            Some(CONFIG_VERSION as i64)
+        );
+        assert!(default_document()
+            .iter()
+            .all(|(key, _)| doc.contains_key(key)));
+
+        let config: Config = toml::from_str(&doc.to_string()).unwrap();
+        assert_eq!(config.warn_after_minutes, 30);
+        assert!(!migrate(&mut doc));
+    }
+
+    #[test]
+    fn migrate_leaves_current_files_alone() {
+        let source = format!("config_version = {}\n", CONFIG_VERSION);
+        let mut doc: DocumentMut = source.parse().unwrap();
+
+        assert!(!migrate(&mut doc));
+        assert_eq!(doc.to_string(), source);
+    }
+
+    #[test]
+    fn invalid_key_is_found_on_the_line_of_the_error() {
+        let source = "warn_after_minutes = 30\ndanger_after_minutes = \"soon\"\n";
+        let e = toml::from_str::<Config>(source).unwrap_err();
+
+        assert_eq!(
+            invalid_key(source, e.message(), e.span()),
+            Some("danger_after_minutes".to_owned())
+        );
+    }
+
+    #[test]
+    fn invalid_key_is_taken_from_a_missing_field() {
+        assert_eq!(
+            invalid_key("", "missing field `name`", None),
+            Some("name".to_owned())
+        );
+        assert_eq!(invalid_key("", "expected a table", None), None);
+    }
+
+    #[test]
+    fn profiles_fall_back_to_the_top_level_values() {
+        let mut config = with_profiles();
+        assert_eq!(config.thresholds(), (30, 50));
+
+        config.select_profile("deep_work").unwrap();
+        assert_eq!(config.profile_name(), Some("deep_work"));
+        assert_eq!(config.thresholds(), (80, 50));
+        assert_eq!(config.window_geometry(), ([200., 100.], [500., 20.]));
+
+        config.select_profile("meetings").unwrap();
+        assert_eq!(config.thresholds(), (30, 50));
+        assert_eq!(config.window_geometry(), ([200., 100.], [10., 20.]));
+    }
+
+    #[test]
+    fn changes_go_to_the_active_profile() {
+        let mut config = with_profiles();
+        config.select_profile("meetings").unwrap();
+
+        config.set_thresholds(15, 25);
+        config.set_window_geometry([300., 150.], [0., 0.]);
+
+        assert_eq!(config.thresholds(), (15, 25));
+        assert_eq!(config.window_geometry(), ([300., 150.], [0., 0.]));
+        assert_eq!(
+            (config.warn_after_minutes, config.danger_after_minutes),
+            (30, 50)
+        );
+        assert_eq!(config.window_size, [200., 100.]);
+    }
+
+    #[test]
+    fn unknown_profiles_are_rejected() {
+        let mut config = with_profiles();
+        config.select_profile("deep_work").unwrap();
+
+        assert_eq!(
+            config.select_profile("gaming"),
+            Err("Unknown profile: gaming".to_owned())
+        );
+        assert_eq!(config.profile_name(), Some("deep_work"));
+
+        config.select_profile("").unwrap();
+        assert_eq!(config.profile_name(), None);
+
+        config.profile = "gaming".to_owned();
+        assert_eq!(config.thresholds(), (30, 50));
+        assert_eq!(config.validate(), Err("Unknown profile: gaming".to_owned()));
+    }
+
+    #[test]
+    fn window_geometry_leaves_the_other_keys_alone() {
+        let source = "# mine\n\
+                      warn_after_minutes = \"soon\"\n\
+                      window_size = [200.0, 100.0] # small\n\
+                      [profiles.deep_work]\n\
+                      warn_after_minutes = 80\n";
+        let floats = |item: &Item| -> Vec<f64> {
+            let array = item.as_array().unwrap();
+            array.iter().filter_map(|value| value.as_float()).collect()
+        };
+        let mut config = Config::default();
+        config.set_window_geometry([300., 150.], [5., 6.]);
+
+        let mut doc: DocumentMut = source.parse().unwrap();
+        set_window_geometry(&mut doc, &config).unwrap();
+        assert!(doc
+            .to_string()
+            .starts_with("# mine\nwarn_after_minutes = \"soon\"\n"));
+        assert!(doc.to_string().contains(" # small\n"));
+        assert_eq!(floats(&doc["window_size"]), [300., 150.]);
+        assert_eq!(floats(&doc["window_position"]), [5., 6.]);
+
+        let mut config = with_profiles();
+        config.select_profile("deep_work").unwrap();
+        config.set_window_geometry([300., 150.], [5., 6.]);
+
+        let mut doc: DocumentMut = source.parse().unwrap();
+        set_window_geometry(&mut doc, &config).unwrap();
+        let profile = &doc["profiles"]["deep_work"];
+        assert_eq!(floats(&doc["window_size"]), [200., 100.]);
+        assert!(doc.get("window_position").is_none());
+        assert_eq!(profile["warn_after_minutes"].as_integer(), Some(80));
+        assert_eq!(floats(&profile["window_size"]), [300., 150.]);
+        assert_eq!(floats(&profile["window_position"]), [5., 6.]);
+    }
+
+    #[test]
+    fn window_position_must_be_on_the_monitor() {
+        let monitor = [1920., 1080.];
+
+        for (position, expected) in [
+            ([40., 40.], true),
+            ([-50., 40.], true),
+            ([1800., 1000.], true),
+            ([1900., 40.], false),
+            ([40., 1060.], false),
+            ([3000., 40.], false),
+            ([-200., 40.], false),
+        ] {
+            let mut config = Config::default();
+            config.set_window_geometry([180., 80.], position);
+
+            let on_monitor = config.window_position_on_monitor(monitor);
+            assert_eq!(on_monitor.is_some(), expected, "{:?}", position);
+        }
+    }
+}
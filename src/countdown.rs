@@ -0,0 +1,169 @@
+use std::time::{Duration, SystemTime};
+
+use crate::Stopwatch;
+
+/// Counts each active stretch down from a target, carrying on into negative overtime.
+#[derive(Debug, Clone)]
+pub struct Countdown {
+    target: u64,
+    start: Option<SystemTime>,
+}
+
+impl Countdown {
+    pub fn new(target: u64) -> Self {
+        Self {
+            target,
+            start: None,
+        }
+    }
+
+    /// Target duration in seconds.
+    pub fn target(&self) -> u64 {
+        self.target
+    }
+
+    /// Seconds left in the active stretch, negative once overdue and `None` while paused.
+    pub fn remaining(&self, stopwatch: &Stopwatch) -> Option<i64> {
+        if stopwatch.is_paused() {
+            return None;
+        }
+
+        Some(self.target as i64 - stopwatch.elapsed() as i64)
+    }
+
+    /// Returns `true` once per active stretch, when it reaches zero.
+    pub fn check(&mut self, stopwatch: &Stopwatch) -> bool {
+        let Some(remaining) = self.remaining(stopwatch) else {
+            return false;
+        };
+        if remaining > 0 || self.start == Some(stopwatch.start()) {
+            return false;
+        }
+
+        self.start = Some(stopwatch.start());
+        true
+    }
+
+    /// Keeps up with [`Stopwatch::rewind_start`], so a countdown that was up stays reported.
+    pub fn rewind(&mut self, by: Duration) {
+        self.start = self
+            .start
+            .map(|start| start.checked_sub(by).unwrap_or(start));
+    }
+}
+
+/// Parses a duration in seconds from `HH:MM:SS`, `MM:SS`, plain minutes or units like `1h30m`.
+pub fn parse_duration(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let invalid = || format!("Invalid duration: {}", input);
+
+    if input.contains(':') {
+        if input.split(':').count() > 3 {
+            return Err(invalid());
+        }
+
+        return input
+            .split(':')
+            .try_fold(0u64, |total, part| {
+                let n = part.parse::<u64>().ok()?;
+                total.checked_mul(60)?.checked_add(n)
+            })
+            .ok_or_else(invalid);
+    }
+
+    if let Ok(minutes) = input.parse::<u64>() {
+        return minutes.checked_mul(60).ok_or_else(invalid);
+    }
+
+    let mut total = 0;
+    let mut number = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        total = number
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(unit))
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+
+    if !number.is_empty() || input.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_format() {
+        assert_eq!(parse_duration("1:02:03"), Ok(3723));
+        assert_eq!(parse_duration("25:00"), Ok(1500));
+        assert_eq!(parse_duration(" 25 "), Ok(1500));
+        assert_eq!(parse_duration("1h30m"), Ok(5400));
+        assert_eq!(parse_duration("90s"), Ok(90));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for input in [
+            "",
+            "1:2:3:4",
+            "1:x",
+            "5 m",
+            "1h30",
+            "m",
+            "99999999999999999999",
+        ] {
+            assert!(parse_duration(input).is_err(), "{:?} parsed", input);
+        }
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        assert!(parse_duration(&u64::MAX.to_string()).is_err());
+        assert!(parse_duration(&format!("{}:00", u64::MAX)).is_err());
+        assert!(parse_duration(&format!("{}h", u64::MAX / 60)).is_err());
+    }
+
+    #[test]
+    fn reports_zero_once_per_active_stretch() {
+        let mut countdown = Countdown::new(60);
+        let mut stopwatch = Stopwatch::new_at(false, SystemTime::now() - Duration::from_secs(90));
+
+        assert_eq!(countdown.remaining(&stopwatch), Some(-30));
+        assert!(countdown.check(&stopwatch));
+        assert!(!countdown.check(&stopwatch));
+
+        stopwatch.toggle();
+        assert_eq!(countdown.remaining(&stopwatch), None);
+        assert!(!countdown.check(&stopwatch));
+    }
+
+    #[test]
+    fn is_not_reported_again_after_the_clock_was_set_back() {
+        let mut countdown = Countdown::new(60);
+        let mut stopwatch = Stopwatch::new_at(false, SystemTime::now() - Duration::from_secs(90));
+        assert!(countdown.check(&stopwatch));
+
+        let by = Duration::from_secs(10);
+        stopwatch.rewind_start(by);
+        countdown.rewind(by);
+
+        assert!(!countdown.check(&stopwatch));
+    }
+}
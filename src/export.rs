@@ -0,0 +1,270 @@
+use std::io::Write;
+
+use chrono::TimeZone;
+
+use crate::session::unix_secs;
+use crate::stats::local_time;
+use crate::Session;
+
+/// Formats sessions can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+    /// iCalendar, with every active session as an event.
+    Ics,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Csv, Format::Json, Format::Ics];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+            Self::Ics => "ics",
+        }
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            "ics" | "ical" | "icalendar" => Ok(Self::Ics),
+            _ => Err(format!("Unknown export format: {}", s)),
+        }
+    }
+}
+
+pub fn export(sessions: &[Session], format: Format, out: &mut impl Write) -> std::io::Result<()> {
+    match format {
+        Format::Csv => export_csv(sessions, out),
+        Format::Json => export_json(sessions, out),
+        Format::Ics => export_ics(sessions, out),
+    }
+}
+
+/// One row per session, with ISO-8601 local timestamps.
+fn export_csv(sessions: &[Session], out: &mut impl Write) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "start,end,kind,duration_seconds,snoozes,automatic,label"
+    )?;
+
+    for session in sessions {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{}",
+            iso8601(session.start),
+            iso8601(session.end),
+            kind(session),
+            session.duration(),
+            session.snoozes,
+            session.automatic,
+            csv_field(session.label.as_deref().unwrap_or_default()),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[derive(serde::Serialize)]
+struct JsonSession<'a> {
+    start: String,
+    end: String,
+    kind: &'static str,
+    duration_seconds: u64,
+    snoozes: u32,
+    automatic: bool,
+    label: Option<&'a str>,
+}
+
+/// A JSON array of sessions, with ISO-8601 local timestamps.
+fn export_json(sessions: &[Session], out: &mut impl Write) -> std::io::Result<()> {
+    let sessions: Vec<JsonSession> = sessions
+        .iter()
+        .map(|session| JsonSession {
+            start: iso8601(session.start),
+            end: iso8601(session.end),
+            kind: kind(session),
+            duration_seconds: session.duration(),
+            snoozes: session.snoozes,
+            automatic: session.automatic,
+            label: session.label.as_deref(),
+        })
+        .collect();
+
+    serde_json::to_writer_pretty(&mut *out, &sessions)?;
+    writeln!(out)
+}
+
+/// A calendar with a VEVENT per active session, pauses are left out.
+fn export_ics(sessions: &[Session], out: &mut impl Write) -> std::io::Result<()> {
+    let stamp = ics_time(unix_secs(std::time::SystemTime::now()));
+
+    write_ics_line(out, "BEGIN:VCALENDAR")?;
+    write_ics_line(out, "VERSION:2.0")?;
+    write_ics_line(out, "PRODID:-//zarthus//stopwatch//EN")?;
+
+    for session in sessions.iter().filter(|s| !s.pause) {
+        let summary = session.label.as_deref().unwrap_or("Active");
+
+        write_ics_line(out, "BEGIN:VEVENT")?;
+        write_ics_line(
+            out,
+            &format!("UID:{}-{}@zarthus_stopwatch", session.start, session.end),
+        )?;
+        write_ics_line(out, &format!("DTSTAMP:{}", stamp))?;
+        write_ics_line(out, &format!("DTSTART:{}", ics_time(session.start)))?;
+        write_ics_line(out, &format!("DTEND:{}", ics_time(session.end)))?;
+        write_ics_line(out, &format!("SUMMARY:{}", ics_text(summary)))?;
+        write_ics_line(out, "END:VEVENT")?;
+    }
+
+    write_ics_line(out, "END:VCALENDAR")
+}
+
+/// Writes a content line, folded after 75 octets and ended with CRLF as RFC 5545 asks.
+fn write_ics_line(out: &mut impl Write, line: &str) -> std::io::Result<()> {
+    let mut written = 0;
+    let mut limit = 75;
+    for (i, c) in line.char_indices() {
+        if i - written + c.len_utf8() > limit {
+            out.write_all(line[written..i].as_bytes())?;
+            out.write_all(b"\r\n ")?;
+            written = i;
+            // The leading space counts towards the continuation's 75 octets.
+            limit = 74;
+        }
+    }
+    out.write_all(line[written..].as_bytes())?;
+    out.write_all(b"\r\n")
+}
+
+fn ics_time(secs: u64) -> String {
+    chrono::Utc
+        .timestamp_opt(secs as i64, 0)
+        .single()
+        .map(|dt| dt.format("%Y%m%dT%H%M%SZ").to_string())
+        .unwrap_or_default()
+}
+
+fn ics_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn kind(session: &Session) -> &'static str {
+    if session.pause {
+        "pause"
+    } else {
+        "active"
+    }
+}
+
+fn iso8601(secs: u64) -> String {
+    local_time(secs)
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ics_lines(line: &str) -> Vec<String> {
+        let mut out = vec![];
+        write_ics_line(&mut out, line).unwrap();
+
+        String::from_utf8(out)
+            .unwrap()
+            .split("\r\n")
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn long_ics_lines_are_folded_at_75_octets() {
+        let line = "x".repeat(200);
+
+        let lines = ics_lines(&line);
+
+        let lengths: Vec<usize> = lines.iter().map(String::len).collect();
+        assert_eq!(lengths, [75, 75, 52, 0]);
+        assert_eq!(lines.concat().replace(' ', ""), line);
+    }
+
+    #[test]
+    fn ics_lines_are_not_folded_inside_a_character() {
+        let line = format!("SUMMARY:{}", "é".repeat(60));
+
+        let lines = ics_lines(&line);
+
+        let lengths: Vec<usize> = lines.iter().map(String::len).collect();
+        assert_eq!(lengths, [74, 55, 0]);
+        assert_eq!(lines.concat().replace(' ', ""), line);
+    }
+
+    #[test]
+    fn short_ics_lines_are_left_alone() {
+        assert_eq!(ics_lines("BEGIN:VEVENT"), ["BEGIN:VEVENT", ""]);
+    }
+
+    #[test]
+    fn ics_has_an_escaped_event_per_active_session() {
+        let sessions = [
+            Session {
+                label: Some("review; part 1, 2".to_owned()),
+                ..Session::new(
+                    false,
+                    std::time::UNIX_EPOCH + std::time::Duration::from_secs(10),
+                    std::time::UNIX_EPOCH + std::time::Duration::from_secs(70),
+                )
+            },
+            Session::new(
+                true,
+                std::time::UNIX_EPOCH + std::time::Duration::from_secs(70),
+                std::time::UNIX_EPOCH + std::time::Duration::from_secs(90),
+            ),
+        ];
+        let mut out = vec![];
+
+        export(&sessions, Format::Ics, &mut out).unwrap();
+
+        let ics = String::from_utf8(out).unwrap();
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+        assert!(ics.contains("DTSTART:19700101T000010Z\r\n"));
+        assert!(ics.contains("DTEND:19700101T000110Z\r\n"));
+        assert!(ics.contains("SUMMARY:review\\; part 1\\, 2\r\n"));
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        assert_eq!(csv_field("review"), "review");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn formats_parse_case_insensitively() {
+        assert_eq!("CSV".parse::<Format>(), Ok(Format::Csv));
+        assert_eq!("ical".parse::<Format>(), Ok(Format::Ics));
+        assert!("xml".parse::<Format>().is_err());
+    }
+}
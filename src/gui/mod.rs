@@ -0,0 +1,5 @@
+//! Parts of the iced frontend.
+
+pub mod settings;
+pub mod stats;
+pub mod timer;
@@ -0,0 +1,135 @@
+use iced::widget::{button, checkbox, column, pick_list, row, scrollable, text, text_input};
+use iced::{Center, Element};
+
+use zarthus_stopwatch::Config;
+
+use crate::Message;
+
+/// Values being edited in the settings view, applied to the config on save.
+///
+/// The thresholds are those of the active profile, switching profiles applies straight away.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub profile: ProfileChoice,
+    pub profiles: Vec<ProfileChoice>,
+    pub warn_after_minutes: String,
+    pub danger_after_minutes: String,
+    pub always_on_top: bool,
+    pub start_unpaused: bool,
+    pub store_last_session: bool,
+    /// Why the last save was refused, or where it went.
+    pub status: Option<String>,
+}
+
+impl Settings {
+    pub fn new(config: &Config) -> Self {
+        let (warn, danger) = config.thresholds();
+        let profiles = std::iter::once(String::new())
+            .chain(config.profiles.keys().cloned())
+            .map(ProfileChoice)
+            .collect();
+
+        Self {
+            profile: ProfileChoice(config.profile_name().unwrap_or_default().to_owned()),
+            profiles,
+            warn_after_minutes: warn.to_string(),
+            danger_after_minutes: danger.to_string(),
+            always_on_top: config.always_on_top,
+            start_unpaused: config.start_unpaused,
+            store_last_session: config.store_last_session,
+            status: None,
+        }
+    }
+
+    /// `config` with the edited values, if they are valid.
+    pub fn apply(&self, config: &Config) -> Result<Config, String> {
+        let mut config = Config {
+            always_on_top: self.always_on_top,
+            start_unpaused: self.start_unpaused,
+            store_last_session: self.store_last_session,
+            ..config.clone()
+        };
+        config.set_thresholds(
+            minutes("warn after", &self.warn_after_minutes)?,
+            minutes("danger after", &self.danger_after_minutes)?,
+        );
+        config.validate()?;
+
+        Ok(config)
+    }
+}
+
+pub fn view(settings: &Settings) -> Element<Message> {
+    let content = column![
+        button(text("back").size(14)).on_press(Message::ShowTimer),
+        pick_list(
+            settings.profiles.as_slice(),
+            Some(settings.profile.clone()),
+            |choice| Message::SelectProfile(choice.0),
+        )
+        .text_size(14),
+        number_row(
+            "warn after",
+            &settings.warn_after_minutes,
+            Message::WarnInput
+        ),
+        number_row(
+            "danger after",
+            &settings.danger_after_minutes,
+            Message::DangerInput
+        ),
+        checkbox("always on top", settings.always_on_top)
+            .on_toggle(Message::SetAlwaysOnTop)
+            .text_size(14),
+        checkbox("start unpaused", settings.start_unpaused)
+            .on_toggle(Message::SetStartUnpaused)
+            .text_size(14),
+        checkbox("restore last session", settings.store_last_session)
+            .on_toggle(Message::SetStoreLastSession)
+            .text_size(14),
+        button(text("save").size(14)).on_press(Message::SaveSettings),
+        text(settings.status.as_deref().unwrap_or_default()).size(12),
+    ]
+    .spacing(6)
+    .padding(10);
+
+    scrollable(content).into()
+}
+
+/// A profile name in the picker, empty for the top-level settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileChoice(pub String);
+
+impl std::fmt::Display for ProfileChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            write!(f, "no profile")
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+fn number_row<'a>(
+    name: &'a str,
+    value: &'a str,
+    on_input: fn(String) -> Message,
+) -> Element<'a, Message> {
+    row![
+        text(name).size(14).width(90),
+        text_input("minutes", value)
+            .on_input(on_input)
+            .size(14)
+            .width(50),
+    ]
+    .spacing(4)
+    .align_y(Center)
+    .into()
+}
+
+fn minutes(name: &str, input: &str) -> Result<u16, String> {
+    input
+        .trim()
+        .parse()
+        .map_err(|_| format!("{} must be a whole number of minutes", name))
+}
@@ -0,0 +1,75 @@
+use iced::widget::{button, column, row, scrollable, text, Column};
+use iced::Element;
+
+use zarthus_stopwatch::{format_text, Format, Stats, Totals};
+
+use crate::Message;
+
+const DAYS_SHOWN: usize = 7;
+const WEEKS_SHOWN: usize = 4;
+
+/// `status` is the outcome of the last export, if any.
+pub fn view<'a>(stats: &'a Stats, status: Option<&'a str>) -> Element<'a, Message> {
+    let days = stats
+        .days
+        .iter()
+        .rev()
+        .take(DAYS_SHOWN)
+        .map(|(day, totals)| rollup(day.format("%a %d %b").to_string(), totals));
+    let weeks = stats
+        .weeks
+        .iter()
+        .rev()
+        .take(WEEKS_SHOWN)
+        .map(|(monday, totals)| rollup(monday.format("week %V").to_string(), totals));
+    let labels = stats
+        .labels
+        .iter()
+        .map(|(label, totals)| rollup(label.clone(), totals));
+
+    let export = Format::ALL
+        .iter()
+        .fold(row![text("export").size(14)], |row, &format| {
+            row.push(button(text(format.extension()).size(14)).on_press(Message::Export(format)))
+        });
+
+    let content = column![
+        button(text("back").size(14)).on_press(Message::ShowTimer),
+        export.spacing(4),
+        text(status.unwrap_or_default()).size(12),
+        text("total").size(18),
+        summary(&stats.total),
+        text("days").size(18),
+        Column::with_children(days).spacing(4),
+        text("weeks").size(18),
+        Column::with_children(weeks).spacing(4),
+        text("labels").size(18),
+        Column::with_children(labels).spacing(4),
+    ]
+    .spacing(6)
+    .padding(10);
+
+    scrollable(content).into()
+}
+
+fn summary<'a>(totals: &Totals) -> Element<'a, Message> {
+    column![
+        line("active", format_text(totals.active, true)),
+        line("breaks", format_text(totals.pause, true)),
+        line("longest", format_text(totals.longest_active, true)),
+        line("over limit", totals.danger_exceeded.to_string()),
+        line("snoozed", totals.snoozes.to_string()),
+    ]
+    .into()
+}
+
+fn rollup<'a>(title: String, totals: &Totals) -> Element<'a, Message> {
+    column![text(title).size(14), summary(totals)].into()
+}
+
+fn line<'a>(name: &str, value: String) -> Element<'a, Message> {
+    text(format!("{:<11}{}", name, value))
+        .font(iced::Font::MONOSPACE)
+        .size(12)
+        .into()
+}
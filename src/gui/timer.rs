@@ -0,0 +1,286 @@
+use std::time::{Duration, SystemTime};
+
+use zarthus_stopwatch::idle::pause_if_idle;
+use zarthus_stopwatch::notify::{interval_message, threshold_message};
+use zarthus_stopwatch::session::unix_secs;
+use zarthus_stopwatch::stats::local_date;
+use zarthus_stopwatch::{
+    format_signed, format_text, Config, Countdown, Intervals, Level, Reminders, Session,
+    SessionStore, Stopwatch, ThresholdAlerts, WarnSettings,
+};
+
+use crate::{load_history, store_session, unstore_session, unstore_sessions};
+
+/// A single stopwatch in the window, along with its store, thresholds and reminders.
+#[derive(Debug)]
+pub struct Timer {
+    /// `None` for the main stopwatch.
+    pub name: Option<String>,
+    pub stopwatch: Stopwatch,
+    pub store: Option<SessionStore>,
+    pub warn: WarnSettings,
+    pub alerts: ThresholdAlerts,
+    pub reminders: Reminders,
+    pub intervals: Option<Intervals>,
+    pub countdown: Option<Countdown>,
+}
+
+/// Summary and body of a notification to send.
+pub type Alert = (&'static str, String);
+
+impl Timer {
+    pub fn new(
+        name: Option<String>,
+        store: Option<SessionStore>,
+        warn: WarnSettings,
+        config: &Config,
+    ) -> Self {
+        let history = load_history(store.as_ref()).unwrap_or_default();
+        let stopwatch = if config.store_last_session {
+            Stopwatch::resume(&history)
+        } else {
+            None
+        };
+
+        Self {
+            name,
+            stopwatch: stopwatch.unwrap_or_else(|| Stopwatch::new(!config.start_unpaused)),
+            store,
+            warn,
+            alerts: ThresholdAlerts::default(),
+            reminders: Reminders::new(config.remind_every_minutes),
+            intervals: Intervals::new(config.intervals.clone()),
+            countdown: None,
+        }
+    }
+
+    /// Every stopwatch from the config, the main one first.
+    pub fn all(config: &Config) -> Vec<Self> {
+        let mut timers = vec![Self::new(
+            None,
+            SessionStore::open_default(config.profile_name()),
+            config.warn_settings(),
+            config,
+        )];
+
+        for stopwatch in &config.stopwatches {
+            timers.push(Self::new(
+                Some(stopwatch.name.clone()),
+                SessionStore::open_named(config.profile_name(), &stopwatch.name),
+                config.warn_settings_for(stopwatch),
+                config,
+            ));
+        }
+
+        timers
+    }
+
+    /// Takes over changed thresholds from a reloaded config, keeping the running session.
+    pub fn reconfigure(&mut self, config: &Config) {
+        let warn = match &self.name {
+            None => Some(config.warn_settings()),
+            Some(name) => config
+                .stopwatches
+                .iter()
+                .find(|stopwatch| stopwatch.name == *name)
+                .map(|stopwatch| config.warn_settings_for(stopwatch)),
+        };
+
+        if let Some(warn) = warn {
+            self.warn = warn;
+        }
+    }
+
+    /// Starts over paused with no breaks counted, storing the current stretch as the end of the
+    /// run so a restart doesn't bring the old one back.
+    pub fn reset(&mut self, config: &Config) {
+        if !self.stopwatch.is_paused() || !self.stopwatch.sessions().is_empty() {
+            let session = self.stopwatch.reset_at(SystemTime::now());
+            store_session(self.store.as_ref(), &session);
+        }
+
+        self.start_over(config);
+    }
+
+    /// Takes back the last toggle, removing the session it stored.
+    pub fn undo_toggle(&mut self) {
+        let Some(session) = self.stopwatch.undo_toggle() else {
+            return;
+        };
+        unstore_session(self.store.as_ref(), &session);
+
+        if let Some(intervals) = self.intervals.as_mut() {
+            intervals.untoggled(&self.stopwatch);
+        }
+    }
+
+    /// Forgets the sessions started today, stored or running, and starts over paused.
+    ///
+    /// A run boundary is stored in their place, or a restart would resume the run before today.
+    pub fn clear_today(&mut self, config: &Config) {
+        let now = SystemTime::now();
+        let today = local_date(unix_secs(now));
+        unstore_sessions(self.store.as_ref(), |session| {
+            local_date(session.start) != today
+        });
+        store_session(self.store.as_ref(), &Session::run_boundary(now));
+
+        self.stopwatch = Stopwatch::new_at(true, now);
+        self.start_over(config);
+    }
+
+    /// Resets everything that tracks the stopwatch, for when it starts over.
+    fn start_over(&mut self, config: &Config) {
+        self.alerts = ThresholdAlerts::default();
+        self.reminders = Reminders::new(config.remind_every_minutes);
+        self.intervals = Intervals::new(config.intervals.clone());
+        self.countdown = None;
+    }
+
+    pub fn toggle(&mut self) {
+        let session = self.stopwatch.toggle();
+        store_session(self.store.as_ref(), &session);
+
+        if let Some(intervals) = self.intervals.as_mut() {
+            intervals.toggled(&self.stopwatch);
+        }
+    }
+
+    /// Advances intervals and checks the countdown and thresholds, returning what to notify.
+    ///
+    /// A snooze that has run out by `now` is ended first, so the reminder it held off is sent.
+    pub fn refresh(&mut self, config: &Config, now: SystemTime) -> Vec<Alert> {
+        if self.reminders.snooze_over(now) {
+            self.reminders.wake(&self.stopwatch);
+        }
+
+        let alerts = [
+            self.advance_intervals(),
+            self.check_countdown(),
+            self.check_thresholds(config),
+        ];
+
+        alerts
+            .into_iter()
+            .flatten()
+            .map(|alert| self.named(alert))
+            .collect()
+    }
+
+    /// Pauses the timer if the user has been away for too long.
+    pub fn pause_if_idle(&mut self, config: &Config, idle_seconds: u64, now: SystemTime) {
+        let after = config.pause_when_idle_minutes as u64 * 60;
+        let Some(session) = pause_if_idle(&mut self.stopwatch, after, idle_seconds, now) else {
+            return;
+        };
+        store_session(self.store.as_ref(), &session);
+
+        if let Some(intervals) = self.intervals.as_mut() {
+            intervals.toggled(&self.stopwatch);
+        }
+    }
+
+    /// Carries on after the wall clock was set back by `by`, as if it hadn't been.
+    ///
+    /// The notifications keep track of the stretch by its start, so they are moved along with it.
+    pub fn rewind(&mut self, by: Duration) {
+        self.stopwatch.rewind_start(by);
+        self.alerts.rewind(by);
+        self.reminders.rewind(by);
+        if let Some(countdown) = self.countdown.as_mut() {
+            countdown.rewind(by);
+        }
+    }
+
+    /// Counts the time between `start` and `end` as a break, like a suspend the user wasn't
+    /// working through.
+    pub fn pause_between(&mut self, start: SystemTime, end: SystemTime) {
+        for session in self.stopwatch.pause_between(start, end) {
+            store_session(self.store.as_ref(), &session);
+        }
+    }
+
+    fn named(&self, (summary, body): Alert) -> Alert {
+        match &self.name {
+            Some(name) => (summary, format!("{}: {}", name, body)),
+            None => (summary, body),
+        }
+    }
+
+    fn advance_intervals(&mut self) -> Option<Alert> {
+        let intervals = self.intervals.as_mut()?;
+        let session = intervals.tick(&mut self.stopwatch, SystemTime::now())?;
+        store_session(self.store.as_ref(), &session);
+
+        // A plan that stopped after missing intervals has nothing to announce.
+        let length = intervals.length(&self.stopwatch)?;
+        Some(interval_message(self.stopwatch.is_paused(), length))
+    }
+
+    fn check_countdown(&mut self) -> Option<Alert> {
+        let countdown = self.countdown.as_mut()?;
+        if !countdown.check(&self.stopwatch) {
+            return None;
+        }
+
+        Some((
+            "Time is up",
+            format!("Counted down {}", format_text(countdown.target(), false)),
+        ))
+    }
+
+    fn check_thresholds(&mut self, config: &Config) -> Option<Alert> {
+        let level = match self.alerts.check(&self.stopwatch, &self.warn) {
+            Some(level) => level,
+            None if self.reminders.check(&self.stopwatch, &self.warn) => Level::Danger,
+            None => return None,
+        };
+        if !config.notifies_on(level) {
+            return None;
+        }
+
+        Some(threshold_message(level, self.stopwatch.elapsed()))
+    }
+
+    /// Seconds left of the countdown or interval, if either is running.
+    pub fn remaining(&self) -> Option<i64> {
+        self.countdown
+            .as_ref()
+            .and_then(|countdown| countdown.remaining(&self.stopwatch))
+            .or_else(|| {
+                self.intervals
+                    .as_ref()
+                    .and_then(|intervals| intervals.remaining(&self.stopwatch))
+            })
+    }
+
+    pub fn time_text(&self) -> String {
+        match self.remaining() {
+            Some(remaining) => format_signed(remaining, false),
+            None => format_text(self.stopwatch.elapsed(), false),
+        }
+    }
+
+    /// How urgently a break is due, a finished countdown always being urgent.
+    pub fn level(&self) -> Level {
+        let countdown_over = self
+            .countdown
+            .as_ref()
+            .and_then(|countdown| countdown.remaining(&self.stopwatch))
+            .is_some_and(|remaining| remaining <= 0);
+        if countdown_over {
+            return Level::Danger;
+        }
+
+        self.warn.level(self.stopwatch.elapsed())
+    }
+
+    /// The stored history, or this run if there is none, followed by the current stretch.
+    pub fn history(&self) -> Vec<Session> {
+        let mut sessions =
+            load_history(self.store.as_ref()).unwrap_or_else(|| self.stopwatch.sessions().to_vec());
+        sessions.push(self.stopwatch.current());
+
+        sessions
+    }
+}
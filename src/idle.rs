@@ -0,0 +1,143 @@
+use std::time::{Duration, SystemTime};
+
+use crate::{Session, Stopwatch};
+
+/// Something that knows how long the user has not touched the keyboard or mouse.
+pub trait IdleMonitor: std::fmt::Debug {
+    /// Seconds since the last user input.
+    fn idle_seconds(&mut self) -> Result<u64, String>;
+}
+
+/// Reports whatever idle time it is given.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct FakeIdleMonitor {
+    pub idle_seconds: u64,
+}
+
+#[cfg(test)]
+impl IdleMonitor for FakeIdleMonitor {
+    fn idle_seconds(&mut self) -> Result<u64, String> {
+        Ok(self.idle_seconds)
+    }
+}
+
+/// Asks the desktop over D-Bus, through GNOME's idle monitor or the freedesktop screensaver.
+#[cfg(feature = "idle")]
+#[derive(Debug)]
+pub struct DbusIdleMonitor {
+    connection: zbus::blocking::Connection,
+}
+
+#[cfg(feature = "idle")]
+impl DbusIdleMonitor {
+    /// Connects to the user's session bus.
+    pub fn session() -> Result<Self, String> {
+        let connection = zbus::blocking::Connection::session()
+            .map_err(|e| format!("Failed to connect to session bus: {}", e))?;
+
+        Ok(Self { connection })
+    }
+
+    fn mutter_idle_millis(&self) -> zbus::Result<u64> {
+        self.connection
+            .call_method(
+                Some("org.gnome.Mutter.IdleMonitor"),
+                "/org/gnome/Mutter/IdleMonitor/Core",
+                Some("org.gnome.Mutter.IdleMonitor"),
+                "GetIdletime",
+                &(),
+            )?
+            .body()
+            .deserialize()
+    }
+
+    fn screensaver_idle_seconds(&self) -> zbus::Result<u32> {
+        self.connection
+            .call_method(
+                Some("org.freedesktop.ScreenSaver"),
+                "/org/freedesktop/ScreenSaver",
+                Some("org.freedesktop.ScreenSaver"),
+                "GetSessionIdleTime",
+                &(),
+            )?
+            .body()
+            .deserialize()
+    }
+}
+
+#[cfg(feature = "idle")]
+impl IdleMonitor for DbusIdleMonitor {
+    fn idle_seconds(&mut self) -> Result<u64, String> {
+        if let Ok(millis) = self.mutter_idle_millis() {
+            return Ok(millis / 1000);
+        }
+
+        self.screensaver_idle_seconds()
+            .map(u64::from)
+            .map_err(|e| format!("Failed to get idle time: {}", e))
+    }
+}
+
+/// Pauses an active stopwatch once the user has been idle for `after_seconds`, 0 never pausing.
+///
+/// The active session is ended when the idleness began rather than now, so the time away counts
+/// as a break. Returns the ended session for storing.
+pub fn pause_if_idle(
+    stopwatch: &mut Stopwatch,
+    after_seconds: u64,
+    idle_seconds: u64,
+    now: SystemTime,
+) -> Option<Session> {
+    if after_seconds == 0 || stopwatch.is_paused() || idle_seconds < after_seconds {
+        return None;
+    }
+
+    let idle_since = now
+        .checked_sub(Duration::from_secs(idle_seconds))?
+        .max(stopwatch.start());
+
+    Some(stopwatch.toggle_automatic_at(idle_since))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn pauses_when_idleness_began() {
+        let mut monitor = FakeIdleMonitor { idle_seconds: 600 };
+        let mut stopwatch = Stopwatch::new_at(false, at(1000));
+
+        let idle = monitor.idle_seconds().unwrap();
+        let session = pause_if_idle(&mut stopwatch, 300, idle, at(2000)).unwrap();
+
+        assert!(!session.pause);
+        assert!(session.automatic);
+        assert_eq!((session.start, session.end), (1000, 1400));
+        assert!(stopwatch.is_paused());
+        assert_eq!(stopwatch.start(), at(1400));
+    }
+
+    #[test]
+    fn idleness_before_the_start_is_not_a_break() {
+        let mut stopwatch = Stopwatch::new_at(false, at(1000));
+
+        let session = pause_if_idle(&mut stopwatch, 300, 1500, at(2000)).unwrap();
+
+        assert_eq!((session.start, session.end), (1000, 1000));
+    }
+
+    #[test]
+    fn short_idleness_or_disabled_does_nothing() {
+        let mut stopwatch = Stopwatch::new_at(false, at(1000));
+
+        assert_eq!(pause_if_idle(&mut stopwatch, 300, 299, at(2000)), None);
+        assert_eq!(pause_if_idle(&mut stopwatch, 0, 1000, at(2000)), None);
+        assert!(!stopwatch.is_paused());
+    }
+}
@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+
+use crate::countdown::parse_duration;
+use crate::session::unix_secs;
+use crate::{Session, SessionStore};
+
+/// Where versions before the session store wrote the sessions of the last run.
+pub fn legacy_log_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("zarthus_counter.log"))
+}
+
+/// Parses a legacy `HH:MM:SS pause|active` log.
+///
+/// The log only held durations and was rewritten on every toggle, so the last session is taken
+/// to end at `modified` and each session before it to end where the next one starts.
+pub fn parse_legacy_log(content: &str, modified: u64) -> Result<Vec<Session>, String> {
+    let mut sessions = vec![];
+    let mut end = modified;
+
+    for (no, line) in content.lines().enumerate().rev() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let invalid = || format!("Invalid line {}: {}", no + 1, line);
+        let mut parts = line.split_whitespace();
+        let duration = parts.next().ok_or_else(invalid)?;
+        let pause = match parts.next() {
+            Some("pause") => true,
+            Some("active") => false,
+            _ => return Err(invalid()),
+        };
+        let duration = parse_duration(duration).map_err(|_| invalid())?;
+
+        let start = end.saturating_sub(duration);
+        sessions.push(Session {
+            pause,
+            start,
+            end,
+            label: None,
+            snoozes: 0,
+            automatic: false,
+            reset: false,
+        });
+        end = start;
+    }
+
+    sessions.reverse();
+    Ok(sessions)
+}
+
+/// Imports the legacy log at `path` into `store`, returning how many sessions were added.
+pub fn import_legacy_log(path: &Path, store: &SessionStore) -> Result<usize, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let modified = std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .map_err(|e| format!("Failed to read metadata of {}: {}", path.display(), e))?;
+
+    let sessions = parse_legacy_log(&content, unix_secs(modified))?;
+
+    store.merge(&sessions)
+}
+
+/// Imports the legacy log from the config directory once, renaming it to `.imported` after.
+///
+/// Returns the number of sessions imported, `None` if there was nothing to import. The log belongs
+/// to the default data directory, so nothing is imported while another one is used.
+pub fn migrate_legacy_log(store: &SessionStore) -> Result<Option<usize>, String> {
+    if !crate::paths::is_default_data_dir() {
+        return Ok(None);
+    }
+    let Some(path) = legacy_log_path().filter(|path| path.exists()) else {
+        return Ok(None);
+    };
+
+    let imported = import_legacy_log(&path, store)?;
+    std::fs::rename(&path, path.with_extension("log.imported"))
+        .map_err(|e| format!("Failed to rename {}: {}", path.display(), e))?;
+
+    Ok(Some(imported))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_log_ends_at_the_modification_time() {
+        let log = "00:10:00 active\n\n00:05:00 pause\n01:00 active\n";
+
+        let sessions = parse_legacy_log(log, 10_000).unwrap();
+
+        let stretches: Vec<_> = sessions.iter().map(|s| (s.pause, s.start, s.end)).collect();
+        assert_eq!(
+            stretches,
+            [
+                (false, 9_040, 9_640),
+                (true, 9_640, 9_940),
+                (false, 9_940, 10_000)
+            ]
+        );
+    }
+
+    #[test]
+    fn legacy_log_with_a_bad_line_is_rejected() {
+        let e = parse_legacy_log("00:10:00 active\n00:05:00 lunch\n", 10_000).unwrap_err();
+
+        assert_eq!(e, "Invalid line 2: 00:05:00 lunch");
+    }
+}
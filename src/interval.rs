@@ -0,0 +1,183 @@
+use std::time::{Duration, SystemTime};
+
+use crate::clock::MIN_GAP;
+use crate::{Session, Stopwatch};
+
+/// Drives a [`Stopwatch`] through fixed work and break lengths, such as pomodoros.
+///
+/// The plan is a list of `[work, break]` minutes that repeats once finished. Nothing happens
+/// until the stopwatch is first started, from then on every interval that runs out toggles it
+/// automatically.
+#[derive(Debug, Clone)]
+pub struct Intervals {
+    plan: Vec<[u16; 2]>,
+    round: usize,
+    running: bool,
+}
+
+impl Intervals {
+    /// Returns `None` if the plan has no work interval longer than zero minutes.
+    pub fn new(mut plan: Vec<[u16; 2]>) -> Option<Self> {
+        plan.retain(|[work, _]| *work > 0);
+        if plan.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            plan,
+            round: 0,
+            running: false,
+        })
+    }
+
+    /// Length in seconds of the interval the stopwatch is in, `None` while waiting to start.
+    pub fn length(&self, stopwatch: &Stopwatch) -> Option<u64> {
+        if !self.running && stopwatch.is_paused() {
+            return None;
+        }
+
+        let [work, pause] = self.plan[self.round];
+        let minutes = if stopwatch.is_paused() { pause } else { work };
+
+        Some(minutes as u64 * 60)
+    }
+
+    /// Seconds left in the current interval, negative once it is overdue.
+    pub fn remaining(&self, stopwatch: &Stopwatch) -> Option<i64> {
+        let length = self.length(stopwatch)?;
+
+        Some(length as i64 - stopwatch.elapsed() as i64)
+    }
+
+    /// Switches to the next interval if the current one has run out by `now`.
+    ///
+    /// If the next one has run out as well, the app wasn't running to see them, like after a
+    /// restart or a suspend. Instead of replaying every missed interval the plan stops, an active
+    /// stopwatch being paused once where its interval ended.
+    pub fn tick(&mut self, stopwatch: &mut Stopwatch, now: SystemTime) -> Option<Session> {
+        if !stopwatch.is_paused() {
+            self.running = true;
+        }
+
+        let end = stopwatch.start() + Duration::from_secs(self.length(stopwatch)?);
+        if now < end {
+            return None;
+        }
+
+        let overdue = now.duration_since(end).unwrap_or_default();
+        if overdue >= Duration::from_secs(self.next_length(stopwatch)).max(MIN_GAP) {
+            self.running = false;
+            if stopwatch.is_paused() {
+                return None;
+            }
+            return Some(stopwatch.toggle_automatic_at(end));
+        }
+
+        let session = stopwatch.toggle_automatic_at(end);
+        self.next_round(stopwatch);
+
+        Some(session)
+    }
+
+    /// Keeps the plan in step after the stopwatch was toggled by hand.
+    pub fn toggled(&mut self, stopwatch: &Stopwatch) {
+        if self.running {
+            self.next_round(stopwatch);
+        }
+        self.running = true;
+    }
+
+    /// Takes back [`Intervals::toggled`] or a [`Intervals::tick`], after the stopwatch's last
+    /// toggle was undone.
+    pub fn untoggled(&mut self, stopwatch: &Stopwatch) {
+        if stopwatch.is_paused() && stopwatch.sessions().is_empty() {
+            self.running = false;
+        } else if stopwatch.is_paused() {
+            self.round = (self.round + self.plan.len() - 1) % self.plan.len();
+        }
+    }
+
+    /// Length in seconds of the interval after the current one.
+    fn next_length(&self, stopwatch: &Stopwatch) -> u64 {
+        let minutes = if stopwatch.is_paused() {
+            self.plan[(self.round + 1) % self.plan.len()][0]
+        } else {
+            self.plan[self.round][1]
+        };
+
+        minutes as u64 * 60
+    }
+
+    fn next_round(&mut self, stopwatch: &Stopwatch) {
+        if !stopwatch.is_paused() {
+            self.round = (self.round + 1) % self.plan.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn waits_for_the_first_start() {
+        let mut intervals = Intervals::new(vec![[25, 5]]).unwrap();
+        let mut stopwatch = Stopwatch::new_at(true, at(0));
+
+        assert_eq!(intervals.length(&stopwatch), None);
+        assert_eq!(intervals.tick(&mut stopwatch, at(10_000)), None);
+        assert!(stopwatch.is_paused());
+    }
+
+    #[test]
+    fn switches_when_an_interval_runs_out() {
+        let mut intervals = Intervals::new(vec![[25, 5], [50, 10]]).unwrap();
+        let mut stopwatch = Stopwatch::new_at(false, at(0));
+
+        assert_eq!(intervals.tick(&mut stopwatch, at(25 * 60 - 1)), None);
+
+        let work = intervals.tick(&mut stopwatch, at(25 * 60 + 1)).unwrap();
+        assert!(!work.pause && work.automatic);
+        assert_eq!((work.start, work.end), (0, 25 * 60));
+        assert_eq!(intervals.length(&stopwatch), Some(5 * 60));
+
+        let pause = intervals.tick(&mut stopwatch, at(30 * 60)).unwrap();
+        assert!(pause.pause);
+        assert!(!stopwatch.is_paused());
+        assert_eq!(intervals.length(&stopwatch), Some(50 * 60));
+    }
+
+    #[test]
+    fn stops_instead_of_replaying_missed_intervals() {
+        let mut intervals = Intervals::new(vec![[25, 5]]).unwrap();
+        let mut stopwatch = Stopwatch::new_at(false, at(0));
+
+        let work = intervals.tick(&mut stopwatch, at(8 * 3600)).unwrap();
+        assert_eq!(work.end, 25 * 60);
+        assert!(stopwatch.is_paused());
+        assert_eq!(intervals.length(&stopwatch), None);
+        assert_eq!(intervals.tick(&mut stopwatch, at(9 * 3600)), None);
+    }
+
+    #[test]
+    fn follows_toggles_by_hand() {
+        let mut intervals = Intervals::new(vec![[25, 5], [50, 10]]).unwrap();
+        let mut stopwatch = Stopwatch::new_at(true, at(0));
+
+        stopwatch.toggle_at(at(1));
+        intervals.toggled(&stopwatch);
+        stopwatch.toggle_at(at(2));
+        intervals.toggled(&stopwatch);
+        stopwatch.toggle_at(at(3));
+        intervals.toggled(&stopwatch);
+        assert_eq!(intervals.length(&stopwatch), Some(50 * 60));
+
+        stopwatch.undo_toggle();
+        intervals.untoggled(&stopwatch);
+        assert_eq!(intervals.length(&stopwatch), Some(5 * 60));
+    }
+}
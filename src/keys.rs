@@ -0,0 +1,386 @@
+use std::str::FromStr;
+
+/// Something a key binding can do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Toggle,
+    Reset,
+    Undo,
+    Stats,
+    Label,
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [
+        Action::Toggle,
+        Action::Reset,
+        Action::Undo,
+        Action::Stats,
+        Action::Label,
+    ];
+
+    /// Name of the action in the `[keys]` config section.
+    pub fn id(self) -> &'static str {
+        match self {
+            Action::Toggle => "toggle",
+            Action::Reset => "reset",
+            Action::Undo => "undo",
+            Action::Stats => "stats",
+            Action::Label => "label",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.id() == id)
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Toggle => "Start or pause the stopwatch",
+            Action::Reset => "Reset the stopwatch",
+            Action::Undo => "Take back the last start or pause",
+            Action::Stats => "Show the stats",
+            Action::Label => "Label the current activity",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub logo: bool,
+}
+
+/// A key with modifiers, written like `space`, `s` or `ctrl+alt+space`.
+///
+/// Keys are named like the toolkit names them, lowercased: a character or a named key such as
+/// `space`, `enter` or `f1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub modifiers: Modifiers,
+    pub key: String,
+}
+
+impl KeyBinding {
+    pub fn matches(&self, key: &str, modifiers: Modifiers) -> bool {
+        self.modifiers == modifiers && self.key == key.to_lowercase()
+    }
+
+    /// The binding in the trigger format of the XDG global shortcuts portal, like `CTRL+space`.
+    pub fn portal_trigger(&self) -> String {
+        let Modifiers {
+            ctrl,
+            alt,
+            shift,
+            logo,
+        } = self.modifiers;
+        let modifiers = [
+            (ctrl, "CTRL"),
+            (alt, "ALT"),
+            (shift, "SHIFT"),
+            (logo, "LOGO"),
+        ];
+
+        modifiers
+            .into_iter()
+            .filter(|(held, _)| *held)
+            .map(|(_, name)| name)
+            .chain([self.key.as_str()])
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts.pop().unwrap_or_default().to_lowercase();
+        if key.is_empty() {
+            return Err(format!("Missing key in binding: {}", s));
+        }
+
+        let mut modifiers = Modifiers::default();
+        for part in parts {
+            match part.to_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "logo" | "super" | "meta" => modifiers.logo = true,
+                other => return Err(format!("Unknown modifier in binding {}: {}", s, other)),
+            }
+        }
+
+        Ok(Self { modifiers, key })
+    }
+}
+
+/// Shortcuts that work while the window is not focused.
+pub trait GlobalHotkeys: std::fmt::Debug {
+    /// Replaces the bound shortcuts with `bindings`.
+    fn bind(&mut self, bindings: &[(Action, KeyBinding)]) -> Result<(), String>;
+
+    /// Actions triggered since the last call.
+    fn poll(&mut self) -> Vec<Action>;
+}
+
+#[cfg(feature = "global_hotkeys")]
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+#[cfg(feature = "global_hotkeys")]
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+#[cfg(feature = "global_hotkeys")]
+const PORTAL_INTERFACE: &str = "org.freedesktop.portal.GlobalShortcuts";
+
+/// Binds shortcuts through the XDG desktop portal, which asks the user to confirm them.
+#[cfg(feature = "global_hotkeys")]
+#[derive(Debug)]
+pub struct PortalHotkeys {
+    connection: zbus::blocking::Connection,
+    session: Option<zbus::zvariant::OwnedObjectPath>,
+    sessions_created: u32,
+    activated: std::sync::mpsc::Receiver<Action>,
+}
+
+#[cfg(feature = "global_hotkeys")]
+impl PortalHotkeys {
+    /// Connects to the user's session bus and starts listening for activated shortcuts.
+    pub fn session() -> Result<Self, String> {
+        let connection = zbus::blocking::Connection::session()
+            .map_err(|e| format!("Failed to connect to session bus: {}", e))?;
+
+        let rule = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface(PORTAL_INTERFACE)
+            .and_then(|rule| rule.member("Activated"))
+            .map_err(|e| format!("Invalid match rule: {}", e))?
+            .build();
+        let messages = zbus::blocking::MessageIterator::for_match_rule(rule, &connection, None)
+            .map_err(|e| format!("Failed to listen for shortcuts: {}", e))?;
+
+        let (sender, activated) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for message in messages.flatten() {
+                let body = message.body();
+                let Ok((_, id, _, _)) = body.deserialize::<(
+                    zbus::zvariant::OwnedObjectPath,
+                    String,
+                    u64,
+                    std::collections::HashMap<String, zbus::zvariant::OwnedValue>,
+                )>() else {
+                    continue;
+                };
+
+                if let Some(action) = Action::from_id(&id) {
+                    if sender.send(action).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            connection,
+            session: None,
+            sessions_created: 0,
+            activated,
+        })
+    }
+
+    /// Opens a new portal session, shortcuts only being bound once per session.
+    fn create_session(&mut self) -> Result<zbus::zvariant::OwnedObjectPath, String> {
+        self.sessions_created += 1;
+        let token = format!(
+            "zarthus_stopwatch_{}_{}",
+            std::process::id(),
+            self.sessions_created
+        );
+        let options = std::collections::HashMap::from([
+            ("handle_token", zbus::zvariant::Value::from(token.as_str())),
+            (
+                "session_handle_token",
+                zbus::zvariant::Value::from(token.as_str()),
+            ),
+        ]);
+
+        self.connection
+            .call_method(
+                Some(PORTAL_DESTINATION),
+                PORTAL_PATH,
+                Some(PORTAL_INTERFACE),
+                "CreateSession",
+                &(options,),
+            )
+            .map_err(|e| format!("Failed to create shortcut session: {}", e))?;
+
+        // The portal spec fixes the session path, so there is no need to wait for the response.
+        let sender = self
+            .connection
+            .unique_name()
+            .ok_or("Not connected to the session bus")?
+            .as_str()
+            .trim_start_matches(':')
+            .replace('.', "_");
+        let path = format!("{}/session/{}/{}", PORTAL_PATH, sender, token);
+
+        zbus::zvariant::OwnedObjectPath::try_from(path)
+            .map_err(|e| format!("Invalid session path: {}", e))
+    }
+
+    fn close_session(&self, session: &zbus::zvariant::OwnedObjectPath) {
+        let closed = self.connection.call_method(
+            Some(PORTAL_DESTINATION),
+            session.as_str(),
+            Some("org.freedesktop.portal.Session"),
+            "Close",
+            &(),
+        );
+        if let Err(e) = closed {
+            eprintln!("Failed to close shortcut session: {}", e);
+        }
+    }
+}
+
+#[cfg(feature = "global_hotkeys")]
+impl GlobalHotkeys for PortalHotkeys {
+    fn bind(&mut self, bindings: &[(Action, KeyBinding)]) -> Result<(), String> {
+        if let Some(session) = self.session.take() {
+            self.close_session(&session);
+        }
+        if bindings.is_empty() {
+            return Ok(());
+        }
+
+        let session = self.create_session()?;
+        let shortcuts: Vec<_> = bindings
+            .iter()
+            .map(|(action, binding)| {
+                let options = std::collections::HashMap::from([
+                    (
+                        "description",
+                        zbus::zvariant::Value::from(action.description()),
+                    ),
+                    (
+                        "preferred_trigger",
+                        zbus::zvariant::Value::from(binding.portal_trigger()),
+                    ),
+                ]);
+
+                (action.id(), options)
+            })
+            .collect();
+        let options: std::collections::HashMap<&str, zbus::zvariant::Value> = Default::default();
+
+        self.connection
+            .call_method(
+                Some(PORTAL_DESTINATION),
+                PORTAL_PATH,
+                Some(PORTAL_INTERFACE),
+                "BindShortcuts",
+                &(&session, shortcuts, "", options),
+            )
+            .map_err(|e| format!("Failed to bind shortcuts: {}", e))?;
+        self.session = Some(session);
+
+        Ok(())
+    }
+
+    fn poll(&mut self) -> Vec<Action> {
+        self.activated.try_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keys;
+
+    #[test]
+    fn parses_modifiers_and_key() {
+        let binding: KeyBinding = "Ctrl+Alt+Space".parse().unwrap();
+
+        assert_eq!(
+            binding.modifiers,
+            Modifiers {
+                ctrl: true,
+                alt: true,
+                ..Modifiers::default()
+            }
+        );
+        assert_eq!(binding.key, "space");
+        assert_eq!(binding.portal_trigger(), "CTRL+ALT+space");
+    }
+
+    #[test]
+    fn rejects_bad_bindings() {
+        assert!("".parse::<KeyBinding>().is_err());
+        assert!("ctrl+".parse::<KeyBinding>().is_err());
+        assert!("hyper+s".parse::<KeyBinding>().is_err());
+    }
+
+    #[test]
+    fn matches_only_with_the_same_modifiers() {
+        let binding: KeyBinding = "shift+r".parse().unwrap();
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::default()
+        };
+
+        assert!(binding.matches("R", shift));
+        assert!(!binding.matches("r", Modifiers::default()));
+    }
+
+    #[test]
+    fn global_bindings_are_only_the_toggle_if_set() {
+        assert!(Keys::default().global_bindings().unwrap().is_empty());
+
+        let keys = Keys {
+            global_toggle: "ctrl+alt+space".to_owned(),
+            ..Keys::default()
+        };
+        let bindings = keys.global_bindings().unwrap();
+
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(bindings[0].0, Action::Toggle);
+        assert_eq!(bindings[0].1.portal_trigger(), "CTRL+ALT+space");
+    }
+
+    #[test]
+    fn invalid_global_toggle_is_an_error() {
+        let keys = Keys {
+            global_toggle: "hyper+space".to_owned(),
+            ..Keys::default()
+        };
+        assert_eq!(
+            keys.global_bindings().unwrap_err(),
+            "Unknown modifier in binding hyper+space: hyper"
+        );
+
+        let keys = Keys {
+            global_toggle: "ctrl+".to_owned(),
+            ..Keys::default()
+        };
+        assert!(keys.global_bindings().is_err());
+    }
+
+    #[test]
+    fn finds_the_action_for_a_key() {
+        let keys = Keys::default();
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Modifiers::default()
+        };
+
+        assert_eq!(
+            keys.action_for("space", Modifiers::default()),
+            Some(Action::Toggle)
+        );
+        assert_eq!(keys.action_for("space", ctrl), None);
+        assert_eq!(
+            keys.action_for("u", Modifiers::default()),
+            Some(Action::Undo)
+        );
+    }
+}
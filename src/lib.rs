@@ -0,0 +1,61 @@
+//! Headless core of the stopwatch: timing, sessions and configuration.
+//!
+//! Nothing in here depends on a GUI, the iced application in `main.rs` is a thin frontend over
+//! [`Stopwatch`].
+
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(unused_variables)]
+#![deny(unsafe_code)]
+
+pub mod clock;
+pub mod config;
+pub mod countdown;
+pub mod export;
+pub mod idle;
+pub mod import;
+pub mod interval;
+pub mod keys;
+pub mod notify;
+pub mod paths;
+pub mod session;
+pub mod stats;
+pub mod stopwatch;
+pub mod tray;
+pub mod warn;
+
+pub use clock::{ClockJump, ClockWatch};
+pub use config::{load_config, ColorScheme, Config, ConfigError, Keys, Profile, StopwatchConfig};
+pub use countdown::Countdown;
+pub use export::Format;
+pub use idle::IdleMonitor;
+pub use interval::Intervals;
+pub use keys::{Action, GlobalHotkeys, KeyBinding};
+pub use notify::{Notifier, Reminders, ThresholdAlerts};
+pub use session::{Session, SessionStore};
+pub use stats::{Stats, Totals};
+pub use stopwatch::Stopwatch;
+pub use tray::{TrayAction, TrayIcon, TrayStatus};
+pub use warn::{Level, WarnSettings};
+
+/// Formats a duration in seconds as `MM:SS`, or `HH:MM:SS` if there are hours or `full` is set.
+#[inline]
+pub fn format_text(seconds: u64, full: bool) -> String {
+    let hours = seconds / 3600;
+    let minutes = (seconds % 3600) / 60;
+    let seconds = seconds % 60;
+
+    if hours != 0 || full {
+        return format!("{:02}:{:02}:{:02}", hours, minutes, seconds);
+    }
+    format!("{:02}:{:02}", minutes, seconds)
+}
+
+/// Like [`format_text`], prefixed with `-` for negative (overdue) durations.
+pub fn format_signed(seconds: i64, full: bool) -> String {
+    let text = format_text(seconds.unsigned_abs(), full);
+    if seconds < 0 {
+        return format!("-{}", text);
+    }
+    text
+}
@@ -3,49 +3,100 @@
 #![deny(unused_variables)]
 #![deny(unsafe_code)]
 
-use std::io::{Read, Write};
 use std::sync::OnceLock;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime};
 
+use iced::keyboard::key::{Key, Named};
 use iced::theme::Theme;
-use iced::widget::{button, column, container, row, text};
+use iced::widget::{button, column, container, pick_list, row, text, text_input, Column};
 use iced::window::{self, Position};
 use iced::Length::Fill;
 use iced::{Center, Element, Task};
 
+use zarthus_stopwatch::config::{config_modified, read_config, save_config, save_window_geometry};
+use zarthus_stopwatch::countdown::parse_duration;
+use zarthus_stopwatch::keys::Modifiers;
+use zarthus_stopwatch::session::recent_labels;
+use zarthus_stopwatch::{
+    export, format_text, load_config, Action, ClockJump, ClockWatch, ColorScheme, Config,
+    ConfigError, Countdown, Format, GlobalHotkeys, IdleMonitor, Level, Notifier, Session,
+    SessionStore, Stats, TrayAction, TrayIcon, TrayStatus,
+};
+
+use gui::settings::Settings;
+use gui::timer::{Alert, Timer};
+
+mod cli;
+mod gui;
+
 extern crate iced;
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-struct Config {
-    warn_after_minutes: u16,
-    danger_after_minutes: u16,
-    window_size: [f32; 2],
-    window_position: [f32; 2],
-    always_on_top: bool,
-    start_unpaused: bool,
-    /// Only supported if feature store_sessions enabled
-    store_last_session: bool,
-}
+pub fn main() -> iced::Result {
+    let args = match cli::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    zarthus_stopwatch::paths::set_overrides(args.config.clone(), args.data_dir.clone());
+    migrate_sessions();
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            warn_after_minutes: 45,
-            danger_after_minutes: 60,
-            window_size: [180., 80.],
-            window_position: [40., 40.],
-            always_on_top: false,
-            start_unpaused: false,
-            store_last_session: true,
+    if let Some(command) = args.command {
+        if let Err(e) = cli::run(command, args.profile.as_deref()) {
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
+        return Ok(());
     }
-}
 
-pub fn main() -> iced::Result {
-    let settings = load_config();
-    let mut state = State::default();
-    if settings.start_unpaused {
-        state.paused = false;
+    let (mut settings, config_errors) = load_config();
+    let mut profile_override = args.profile.map(|profile| ProfileOverride {
+        profile,
+        saved: String::new(),
+    });
+    if let Some(profile_override) = &mut profile_override {
+        if let Err(e) = profile_override.apply(&mut settings) {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    }
+    let (window_size, window_position) = settings.window_geometry();
+    let mut state = State {
+        timers: Timer::all(&settings),
+        selected: 0,
+        screen: Screen::Timer,
+        config: settings.clone(),
+        profile_override,
+        notifier: open_notifier(),
+        idle: open_idle_monitor(),
+        hotkeys: open_hotkeys(),
+        tray: open_tray(),
+        clock: ClockWatch::default(),
+        gap: None,
+        discard: None,
+        countdown_input: String::new(),
+        countdown_error: None,
+        label_input: String::new(),
+        editing_label: false,
+        recent_labels: vec![],
+        export_status: None,
+        config_warning: config_warning(&config_errors),
+        config_fallback: config_errors.iter().any(|e| !e.is_warning()),
+        config_modified: config_modified(),
+        window_size,
+        window_position,
+    };
+    state.recent_labels = recent_labels(&state.timer().history(), RECENT_LABELS);
+    state.bind_hotkeys();
+    // Like in the window, counting down from 0 is no countdown at all.
+    if let Some(seconds) = args.countdown.filter(|seconds| *seconds > 0) {
+        let timer = state.timer_mut();
+        timer.countdown = Some(Countdown::new(seconds));
+        if timer.stopwatch.is_paused() {
+            timer.toggle();
+        }
     }
 
     let icon = match window::icon::from_file_data(include_bytes!("../resource/icon.png"), None) {
@@ -56,274 +107,1175 @@ pub fn main() -> iced::Result {
         }
     };
 
-    WARN_SETTINGS
-        .set(WarnSettings {
-            warn_after: settings.warn_after_minutes as u64 * 60,
-            danger_after: settings.danger_after_minutes as u64 * 60,
-        })
-        .expect("Failed to set warn settings");
+    let position = settings
+        .window_position_on_screen()
+        .map_or(Position::Default, |_| {
+            Position::SpecificWith(opening_position)
+        });
+    OPENING_CONFIG.get_or_init(|| settings.clone());
 
     iced::application::application("Stopwatch", State::update, State::view)
         .window(window::Settings {
-            size: iced::Size::from(settings.window_size),
-            position: Position::Specific(iced::Point::from(settings.window_position)),
+            size: iced::Size::from(window_size),
+            position,
             min_size: Some([180., 80.].into()),
             visible: true,
             resizable: true,
             transparent: true,
-            level: if settings.always_on_top {
-                window::Level::AlwaysOnTop
-            } else {
-                window::Level::Normal
-            },
+            level: window_level(settings.always_on_top),
             icon,
-            exit_on_close_request: true,
+            exit_on_close_request: false,
             ..Default::default()
         })
         .antialiasing(true)
-        .theme(|_| {
-            let theme = Theme::Dark;
-
-            Theme::custom(
-                "Custom".to_owned(),
-                iced::theme::palette::Palette {
-                    background: theme.palette().background,
-                    text: theme.palette().text,
-                    primary: {
-                        // todo ensure same bg
-                        let col = theme.palette().background.scale_alpha(0.);
-
-                        col
-                    },
-                    success: theme.palette().success,
-                    danger: theme.palette().danger,
-                },
-            )
-        })
+        .theme(State::theme)
         .subscription(State::subscription)
         .run_with(|| (state, Task::none()))
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 struct State {
-    paused: bool,
-    start: SystemTime,
-    sessions: Vec<Session>,
+    timers: Vec<Timer>,
+    selected: usize,
+    screen: Screen,
+    config: Config,
+    notifier: Option<Box<dyn Notifier>>,
+    idle: Option<Box<dyn IdleMonitor>>,
+    hotkeys: Option<Box<dyn GlobalHotkeys>>,
+    tray: Option<Box<dyn TrayIcon>>,
+    clock: ClockWatch,
+    /// Start and end of a suspend the user hasn't said how to count yet.
+    gap: Option<(SystemTime, SystemTime)>,
+    discard: Option<Discard>,
+    countdown_input: String,
+    countdown_error: Option<String>,
+    label_input: String,
+    /// Shows the label input while a timer is running.
+    editing_label: bool,
+    recent_labels: Vec<String>,
+    export_status: Option<String>,
+    config_warning: Option<String>,
+    /// The config is the defaults standing in for a file that failed to load, which is left alone.
+    config_fallback: bool,
+    /// Modification time of the config file when it was last read.
+    config_modified: Option<SystemTime>,
+    profile_override: Option<ProfileOverride>,
+    /// Geometry of the window as last moved or resized, saved on close.
+    window_size: [f32; 2],
+    window_position: [f32; 2],
 }
 
+/// A profile picked with `--profile`, used for this run only instead of the one in the config.
+///
+/// It lasts until another profile is picked in the settings.
 #[derive(Debug)]
-struct WarnSettings {
-    warn_after: u64,
-    danger_after: u64,
+struct ProfileOverride {
+    profile: String,
+    /// The profile in the config file, which is what gets saved.
+    saved: String,
 }
 
-static WARN_SETTINGS: OnceLock<WarnSettings> = OnceLock::new();
+impl ProfileOverride {
+    /// Switches `config` as read from the file to the overriding profile.
+    fn apply(&mut self, config: &mut Config) -> Result<(), String> {
+        self.saved = config.profile.clone();
+        config.select_profile(&self.profile)
+    }
+}
 
-#[derive(Debug, Clone, Copy)]
-struct Session {
-    pause: bool,
-    start: u64,
-    end: u64,
+/// Something that throws away tracked time, waiting for the user to confirm it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Discard {
+    Reset,
+    Undo,
+    ClearToday,
 }
 
-impl Session {
-    fn new(pause: bool, start: SystemTime) -> Self {
-        Self {
-            pause,
-            start: start
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            end: SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+impl Discard {
+    fn question(self) -> &'static str {
+        match self {
+            Discard::Reset => "reset the timer?",
+            Discard::Undo => "undo the last start or pause?",
+            Discard::ClearToday => "clear today's sessions?",
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
+enum Screen {
+    Timer,
+    Stats(Stats),
+    Settings(Settings),
+}
+
+#[derive(Debug, Clone)]
 pub enum Message {
     Toggle,
+    ToggleTimer(usize),
+    Select(usize),
+    SelectNext,
+    SelectPrevious,
     Refresh,
+    ShowStats,
+    ShowTimer,
+    Export(Format),
+    Snooze(u16),
+    CountdownInput(String),
+    SetCountdown,
+    LabelInput(String),
+    SetLabel,
+    SelectLabel(String),
+    DismissWarning,
+    ReloadConfig,
+    ShowSettings,
+    WarnInput(String),
+    DangerInput(String),
+    SetAlwaysOnTop(bool),
+    SetStartUnpaused(bool),
+    SetStoreLastSession(bool),
+    SaveSettings,
+    SelectProfile(String),
+    CountGapAsBreak,
+    KeyPressed(Key, iced::keyboard::Modifiers),
+    Reset,
+    Undo,
+    ClearToday,
+    ConfirmDiscard,
+    CancelDiscard,
+    EditLabel,
+    ShowWindow,
+    Quit,
+    DismissGap,
+    WindowMoved(iced::Point),
+    WindowResized(iced::Size),
+    CloseRequested(window::Id),
 }
 
-impl Default for State {
-    fn default() -> Self {
-        Self {
-            paused: true,
-            start: SystemTime::now(),
-            sessions: vec![],
-        }
-    }
-}
+const SNOOZE_MINUTES: [u16; 3] = [5, 10, 15];
+const RECENT_LABELS: usize = 10;
+/// How often the config file is checked for changes.
+const CONFIG_POLL_SECS: u64 = 2;
+/// The config the window opens with, for [`opening_position`] which can't capture it.
+static OPENING_CONFIG: OnceLock<Config> = OnceLock::new();
 
 impl State {
-    fn update(&mut self, message: Message) {
+    fn update(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::Toggle => self.toggle_pause(),
-            _ => {}
+            Message::Toggle => self.timer_mut().toggle(),
+            Message::ToggleTimer(index) => {
+                self.select(index);
+                self.timer_mut().toggle();
+            }
+            Message::Select(index) => self.select(index),
+            Message::SelectNext => self.select(self.selected + 1),
+            Message::SelectPrevious => self.select(self.selected.saturating_sub(1)),
+            Message::Refresh => {
+                self.check_clock();
+                self.pause_idle_timers();
+                let now = SystemTime::now();
+                let alerts: Vec<Alert> = self
+                    .timers
+                    .iter_mut()
+                    .flat_map(|timer| timer.refresh(&self.config, now))
+                    .collect();
+                self.notify_all(alerts);
+                self.update_tray();
+                return Task::batch([self.poll_hotkeys(), self.poll_tray()]);
+            }
+            Message::ShowStats => self.show_stats(),
+            Message::ShowTimer => self.screen = Screen::Timer,
+            Message::Export(format) => {
+                self.export_status = Some(match self.export(format) {
+                    Ok(path) => format!("saved to {}", path.display()),
+                    Err(e) => e,
+                });
+            }
+            Message::Snooze(minutes) => {
+                let timer = self.timer_mut();
+                timer.reminders.snooze(&mut timer.stopwatch, minutes);
+            }
+            Message::CountdownInput(input) => self.countdown_input = input,
+            Message::SetCountdown => self.set_countdown(),
+            Message::LabelInput(input) => self.label_input = input,
+            Message::SetLabel => {
+                let label = std::mem::take(&mut self.label_input);
+                self.set_label(label);
+            }
+            Message::SelectLabel(label) => self.set_label(label),
+            Message::DismissWarning => self.config_warning = None,
+            Message::ReloadConfig => return self.reload_config(),
+            Message::ShowSettings => self.screen = Screen::Settings(Settings::new(&self.config)),
+            Message::SaveSettings => return self.save_settings(),
+            Message::SelectProfile(name) => return self.select_profile(name),
+            Message::CountGapAsBreak => {
+                if let Some((start, end)) = self.gap.take() {
+                    for timer in &mut self.timers {
+                        timer.pause_between(start, end);
+                    }
+                }
+            }
+            Message::DismissGap => self.gap = None,
+            Message::KeyPressed(key, modifiers) => {
+                if let Some(message) = self.key_message(&key, modifiers) {
+                    return self.update(message);
+                }
+            }
+            Message::Reset => self.discard = Some(Discard::Reset),
+            Message::Undo => {
+                if !self.timer().stopwatch.sessions().is_empty() {
+                    self.discard = Some(Discard::Undo);
+                }
+            }
+            Message::ClearToday => self.discard = Some(Discard::ClearToday),
+            Message::ConfirmDiscard => {
+                let Some(discard) = self.discard.take() else {
+                    return Task::none();
+                };
+                let timer = &mut self.timers[self.selected];
+                match discard {
+                    Discard::Reset => timer.reset(&self.config),
+                    Discard::Undo => timer.undo_toggle(),
+                    Discard::ClearToday => timer.clear_today(&self.config),
+                }
+            }
+            Message::CancelDiscard => self.discard = None,
+            Message::EditLabel => {
+                self.editing_label = true;
+                return text_input::focus(label_input_id());
+            }
+            Message::WarnInput(input) => {
+                self.edit_settings(|settings| settings.warn_after_minutes = input)
+            }
+            Message::DangerInput(input) => {
+                self.edit_settings(|settings| settings.danger_after_minutes = input)
+            }
+            Message::SetAlwaysOnTop(on) => {
+                self.edit_settings(|settings| settings.always_on_top = on)
+            }
+            Message::SetStartUnpaused(on) => {
+                self.edit_settings(|settings| settings.start_unpaused = on)
+            }
+            Message::SetStoreLastSession(on) => {
+                self.edit_settings(|settings| settings.store_last_session = on)
+            }
+            Message::WindowMoved(position) => self.window_position = [position.x, position.y],
+            Message::WindowResized(size) => self.window_size = [size.width, size.height],
+            Message::CloseRequested(id) => {
+                self.save_window();
+                if self.tray.is_some() && self.config.minimize_to_tray {
+                    return window::change_mode(id, window::Mode::Hidden);
+                }
+                return window::close(id);
+            }
+            Message::ShowWindow => {
+                return window::get_latest().and_then(|id| {
+                    window::change_mode(id, window::Mode::Windowed).chain(window::gain_focus(id))
+                });
+            }
+            Message::Quit => {
+                self.save_window();
+                return window::get_latest().and_then(window::close);
+            }
         }
+
+        Task::none()
+    }
+
+    fn theme(&self) -> Theme {
+        let theme = match self.config.theme {
+            ColorScheme::Dark => Theme::Dark,
+            ColorScheme::Light => Theme::Light,
+        };
+
+        Theme::custom(
+            "Custom".to_owned(),
+            iced::theme::palette::Palette {
+                background: theme.palette().background,
+                text: theme.palette().text,
+                primary: {
+                    // todo ensure same bg
+                    let col = theme.palette().background.scale_alpha(0.);
+
+                    col
+                },
+                success: theme.palette().success,
+                danger: theme.palette().danger,
+            },
+        )
     }
 
     fn view(&self) -> Element<Message> {
-        let timer = if self.paused {
-            let time_passed_seconds = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-                - match self.sessions.last() {
-                    Some(s) => s.end,
-                    None => UNIX_EPOCH.elapsed().unwrap().as_secs(),
-                };
+        match &self.screen {
+            Screen::Timer => {}
+            Screen::Stats(stats) => return gui::stats::view(stats, self.export_status.as_deref()),
+            Screen::Settings(settings) => return gui::settings::view(settings),
+        }
+
+        let timers = Column::with_children(
+            self.timers
+                .iter()
+                .enumerate()
+                .map(|(index, timer)| self.timer_row(index, timer)),
+        )
+        .spacing(4)
+        .align_x(Center);
 
-            button(text(format_text(time_passed_seconds, false)).font(iced::Font::MONOSPACE))
+        let timer = self.timer();
+        let bottom_row = if !timer.stopwatch.is_paused() {
+            if timer.level() == Level::Danger {
+                self.snooze_row()
+            } else {
+                row![text(timer.stopwatch.label().unwrap_or_default()).size(14)]
+            }
         } else {
-            let time_passed_seconds = SystemTime::now()
-                .duration_since(self.start)
-                .unwrap()
-                .as_secs();
-            let highlight_color = highlight_col(&time_passed_seconds);
-
-            button(
-                text(format_text(time_passed_seconds, false))
-                    .color(highlight_color)
-                    .font(iced::Font::MONOSPACE)
-                    .size(32),
-            )
-        }
-        .on_press(Message::Toggle);
-
-        let bottom_row = if !self.paused {
-            row![]
+            let pauses = text(format!("breaks: {}", timer.stopwatch.breaks()))
+                .size(20)
+                .width(100);
+            let stats = button(text("stats").size(14)).on_press(Message::ShowStats);
+            let settings = button(text("settings").size(14)).on_press(Message::ShowSettings);
+
+            row![pauses, stats, settings].align_y(Center)
+        };
+
+        let countdown_row = if timer.stopwatch.is_paused() {
+            let input = text_input("countdown", &self.countdown_input)
+                .on_input(Message::CountdownInput)
+                .on_submit(Message::SetCountdown)
+                .size(14)
+                .width(90);
+            let error = text(self.countdown_error.as_deref().unwrap_or_default()).size(12);
+
+            column![input, error, self.label_row()]
+        } else if self.editing_label {
+            column![self.label_row()]
         } else {
-            let pauses = text(format!(
-                "breaks: {}",
-                self.sessions.iter().filter(|s| s.pause).count()
-            ))
-            .size(20)
-            .width(100);
+            column![]
+        };
+
+        let warning = match &self.config_warning {
+            Some(warning) => row![
+                text(warning.as_str())
+                    .size(12)
+                    .color(level_col(Level::Danger)),
+                button(text("x").size(12)).on_press(Message::DismissWarning),
+            ]
+            .spacing(4),
+            None => row![],
+        };
 
-            row![pauses]
+        let gap = match self.gap {
+            Some((start, end)) => {
+                let away = end.duration_since(start).unwrap_or_default().as_secs();
+                row![
+                    text(format!(
+                        "away {}, count as break?",
+                        format_text(away, false)
+                    ))
+                    .size(12),
+                    button(text("yes").size(12)).on_press(Message::CountGapAsBreak),
+                    button(text("no").size(12)).on_press(Message::DismissGap),
+                ]
+                .spacing(4)
+                .align_y(Center)
+            }
+            None => row![],
         };
 
-        container(column![row![timer], bottom_row].align_x(Center))
+        let discard = match self.discard {
+            Some(discard) => row![
+                text(discard.question()).size(12),
+                button(text("yes").size(12)).on_press(Message::ConfirmDiscard),
+                button(text("no").size(12)).on_press(Message::CancelDiscard),
+            ],
+            None => {
+                let undo = button(text("undo").size(12)).on_press_maybe(
+                    (!timer.stopwatch.sessions().is_empty()).then_some(Message::Undo),
+                );
+                row![
+                    button(text("reset").size(12)).on_press(Message::Reset),
+                    undo,
+                    button(text("clear today").size(12)).on_press(Message::ClearToday),
+                ]
+            }
+        }
+        .spacing(4)
+        .align_y(Center);
+
+        container(column![warning, gap, timers, bottom_row, countdown_row, discard].align_x(Center))
             .padding(10)
             .center_x(Fill)
             .center_y(Fill)
             .into()
     }
 
+    fn timer_row<'a>(&'a self, index: usize, timer: &'a Timer) -> Element<'a, Message> {
+        let time = if timer.stopwatch.is_paused() {
+            text(timer.time_text()).font(iced::Font::MONOSPACE)
+        } else {
+            text(timer.time_text())
+                .color(level_col(timer.level()))
+                .font(iced::Font::MONOSPACE)
+                .size(32)
+        };
+        let time = button(time).on_press(Message::ToggleTimer(index));
+
+        if self.timers.len() == 1 {
+            return time.into();
+        }
+
+        let marker = if index == self.selected { "> " } else { "  " };
+        let name = button(
+            text(format!(
+                "{}{}",
+                marker,
+                timer.name.as_deref().unwrap_or("main")
+            ))
+            .size(14),
+        )
+        .on_press(Message::Select(index));
+
+        row![name, time].spacing(4).align_y(Center).into()
+    }
+
+    fn timer(&self) -> &Timer {
+        &self.timers[self.selected]
+    }
+
+    fn timer_mut(&mut self) -> &mut Timer {
+        &mut self.timers[self.selected]
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.timers.len() - 1);
+    }
+
+    fn label_row(&self) -> iced::widget::Row<Message> {
+        let label = self.timer().stopwatch.label();
+        let input = text_input(label.unwrap_or("label"), &self.label_input)
+            .id(label_input_id())
+            .on_input(Message::LabelInput)
+            .on_submit(Message::SetLabel)
+            .size(14)
+            .width(90);
+        let recent = pick_list(
+            self.recent_labels.as_slice(),
+            label.map(str::to_owned),
+            Message::SelectLabel,
+        )
+        .placeholder("recent")
+        .text_size(14);
+
+        row![input, recent].spacing(4)
+    }
+
+    /// Labels the selected timer's activity, an empty label removing it.
+    fn set_label(&mut self, label: String) {
+        self.editing_label = false;
+        let label = label.trim().to_owned();
+        if label.is_empty() {
+            self.timer_mut().stopwatch.set_label(None);
+            return;
+        }
+
+        self.recent_labels.retain(|recent| *recent != label);
+        self.recent_labels.insert(0, label.clone());
+        self.recent_labels.truncate(RECENT_LABELS);
+        self.timer_mut().stopwatch.set_label(Some(label));
+    }
+
+    fn set_countdown(&mut self) {
+        self.countdown_error = None;
+
+        let countdown = if self.countdown_input.trim().is_empty() {
+            None
+        } else {
+            match parse_duration(&self.countdown_input) {
+                Ok(0) => None,
+                Ok(seconds) => Some(Countdown::new(seconds)),
+                Err(e) => {
+                    self.countdown_error = Some(e);
+                    return;
+                }
+            }
+        };
+
+        self.timer_mut().countdown = countdown;
+    }
+
+    fn snooze_row(&self) -> iced::widget::Row<Message> {
+        if let Some(minutes) = self.timer().reminders.snoozed() {
+            return row![text(format!("snoozed {}m", minutes)).size(14)];
+        }
+
+        SNOOZE_MINUTES
+            .iter()
+            .fold(row![text("snooze").size(14)], |row, &minutes| {
+                row.push(
+                    button(text(minutes.to_string()).size(14)).on_press(Message::Snooze(minutes)),
+                )
+            })
+            .spacing(4)
+            .align_y(Center)
+    }
+
     fn subscription(&self) -> iced::Subscription<Message> {
-        iced::Subscription::batch(vec![iced::time::every(Duration::from_millis(
-            if self.paused { 1000 } else { 500 },
-        ))
-        .map(|_| Message::Refresh)])
+        let refresh_millis = if self.timers.iter().all(|timer| timer.stopwatch.is_paused()) {
+            1000
+        } else {
+            500
+        };
+        iced::Subscription::batch(vec![
+            iced::time::every(Duration::from_millis(refresh_millis)).map(|_| Message::Refresh),
+            iced::time::every(Duration::from_secs(CONFIG_POLL_SECS)).map(|_| Message::ReloadConfig),
+            iced::event::listen_with(|event, _, id| match event {
+                iced::Event::Window(window::Event::Moved(position)) => {
+                    Some(Message::WindowMoved(position))
+                }
+                iced::Event::Window(window::Event::Resized(size)) => {
+                    Some(Message::WindowResized(size))
+                }
+                iced::Event::Window(window::Event::CloseRequested) => {
+                    Some(Message::CloseRequested(id))
+                }
+                _ => None,
+            }),
+            iced::keyboard::on_key_press(|key, modifiers| {
+                Some(Message::KeyPressed(key, modifiers))
+            }),
+        ])
     }
 
-    fn toggle_pause(&mut self) {
-        self.sessions.push(Session::new(self.paused, self.start));
-        if self.paused {
-            self.start = SystemTime::now();
+    /// Applies the config file again if it changed since it was last read.
+    ///
+    /// A file that no longer parses is reported and the previous config is kept.
+    fn reload_config(&mut self) -> Task<Message> {
+        let modified = config_modified();
+        if modified.is_none() || modified == self.config_modified {
+            return Task::none();
         }
-        self.paused = !self.paused;
-        match store_sessions(&self.sessions) {
-            Ok(_) => {}
-            Err(e) => eprintln!("Failed to store sessions: {}", e),
+        self.config_modified = modified;
+
+        let (config, warnings) = match read_config() {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                self.config_warning = Some(format!("{}, keeping the previous config", e));
+                return Task::none();
+            }
+        };
+        // Reading may have upgraded the file, which is no reason to read it again.
+        self.config_modified = config_modified();
+        self.config_warning = config_warning(&warnings);
+        self.config_fallback = false;
+
+        let mut config = config;
+        if let Some(profile_override) = &mut self.profile_override {
+            if let Err(e) = profile_override.apply(&mut config) {
+                self.config_warning = Some(format!("{}, keeping the previous config", e));
+                return Task::none();
+            }
         }
+
+        self.apply_config(config)
     }
-}
 
-#[inline]
-fn format_text(seconds: u64, full: bool) -> String {
-    let hours = seconds / 3600;
-    let minutes = (seconds % 3600) / 60;
-    let seconds = seconds % 60;
+    /// Switches to `config`, keeping the running sessions.
+    ///
+    /// Except when the profile changed: a different profile brings its own timers and window
+    /// geometry, the running timers of the old one being paused so their sessions end up in its
+    /// history.
+    fn apply_config(&mut self, config: Config) -> Task<Message> {
+        let profile_changed = config.profile_name() != self.config.profile_name();
+        let level_changed = config.always_on_top != self.config.always_on_top;
+        let hotkeys_changed = config.keys.global_toggle != self.config.keys.global_toggle;
+
+        if profile_changed {
+            for timer in &mut self.timers {
+                if !timer.stopwatch.is_paused() {
+                    timer.toggle();
+                }
+            }
+            self.timers = Timer::all(&config);
+            self.selected = 0;
+            self.recent_labels = recent_labels(&self.timers[0].history(), RECENT_LABELS);
+        } else {
+            for timer in &mut self.timers {
+                timer.reconfigure(&config);
+            }
+        }
+        self.config = config;
+
+        if hotkeys_changed {
+            self.bind_hotkeys();
+        }
 
-    if hours != 0 || full {
-        return format!("{:02}:{:02}:{:02}", hours, minutes, seconds);
+        let mut tasks = vec![];
+        if level_changed {
+            let level = window_level(self.config.always_on_top);
+            tasks.push(window::get_latest().and_then(move |id| window::change_level(id, level)));
+        }
+        if profile_changed {
+            tasks.push(self.restore_window());
+        }
+
+        Task::batch(tasks)
     }
-    format!("{:02}:{:02}", minutes, seconds)
-}
 
-#[inline]
-fn highlight_col(seconds: &u64) -> iced::Color {
-    let warn_settings = WARN_SETTINGS.get().unwrap();
+    /// Moves and resizes the window to the geometry of the current profile.
+    fn restore_window(&mut self) -> Task<Message> {
+        let (size, position) = self.config.window_geometry();
+        self.window_size = size;
+        self.window_position = position;
+        let position = self.config.window_position_on_screen();
 
-    if warn_settings.danger_after == 0 && warn_settings.warn_after == 0 {
-        return iced::Color::from_rgb8(0, 0, 0);
+        window::get_latest().and_then(move |id| {
+            let resize = window::resize(id, iced::Size::from(size));
+            match position {
+                Some(position) => resize.chain(window::move_to(id, iced::Point::from(position))),
+                None => resize,
+            }
+        })
     }
 
-    if *seconds > warn_settings.danger_after {
-        return iced::Color::from_rgb8(255, 0, 0);
+    /// Switches to another profile and remembers it in the config, ending a `--profile` override.
+    ///
+    /// The window geometry is kept for the profile being left first.
+    fn select_profile(&mut self, name: String) -> Task<Message> {
+        let mut config = self.with_window_geometry();
+        if let Err(e) = config.select_profile(&name) {
+            self.edit_settings(|settings| settings.status = Some(e));
+            return Task::none();
+        }
+        self.profile_override = None;
+
+        let result = self.save(&config).map_err(|e| e.to_string());
+        if let Err(e) = result {
+            self.edit_settings(|settings| settings.status = Some(e));
+            return Task::none();
+        }
+
+        self.config_modified = config_modified();
+        let task = self.apply_config(config);
+        if let Screen::Settings(_) = self.screen {
+            self.screen = Screen::Settings(Settings::new(&self.config));
+        }
+
+        task
     }
 
-    if *seconds > warn_settings.warn_after {
-        return iced::Color::from_rgb8(255, 255, 0);
+    fn edit_settings(&mut self, edit: impl FnOnce(&mut Settings)) {
+        if let Screen::Settings(settings) = &mut self.screen {
+            edit(settings);
+        }
     }
 
-    iced::Color::from_rgb8(0, 255, 0)
-}
+    /// Validates and writes the edited settings, applying them if that worked.
+    fn save_settings(&mut self) -> Task<Message> {
+        let Screen::Settings(settings) = &mut self.screen else {
+            return Task::none();
+        };
+
+        let config = match settings.apply(&self.config) {
+            Ok(config) => config,
+            Err(e) => {
+                settings.status = Some(e);
+                return Task::none();
+            }
+        };
+        if let Err(e) = self.save(&config) {
+            self.edit_settings(|settings| settings.status = Some(e.to_string()));
+            return Task::none();
+        }
+        self.edit_settings(|settings| settings.status = Some("saved".to_owned()));
 
-fn load_config() -> Config {
-    let config_path = dirs::config_dir().unwrap().join("zarthus_counter.toml");
+        self.config_modified = config_modified();
+        self.apply_config(config)
+    }
 
-    let config = if !config_path.exists() {
-        let config = Config::default();
+    /// Writes the window geometry to the config if it changed, so the next start opens it there.
+    ///
+    /// A position that is off-screen, like that of a minimized window, is not saved.
+    fn save_window(&mut self) {
+        let config = self.with_window_geometry();
+        if config.window_geometry() == self.config.window_geometry() {
+            return;
+        }
 
-        let file = std::fs::File::create(config_path).unwrap();
-        let mut writer = std::io::BufWriter::new(file);
-        let toml = toml::to_string_pretty(&config).unwrap();
+        match self.writable().and_then(|()| save_window_geometry(&config)) {
+            Ok(()) => {
+                self.config = config;
+                self.config_modified = config_modified();
+            }
+            Err(e) => eprintln!("Failed to save the window geometry: {}", e),
+        }
+    }
 
-        writer.write_all(toml.as_bytes()).unwrap();
+    /// Writes `config` to the file, which keeps its own profile while `--profile` overrides it.
+    fn save(&self, config: &Config) -> Result<(), ConfigError> {
+        self.writable()?;
+
+        match &self.profile_override {
+            Some(profile_override) => save_config(&Config {
+                profile: profile_override.saved.clone(),
+                ..config.clone()
+            }),
+            None => save_config(config),
+        }
+    }
+
+    /// Fails while the config is a fallback, so saving it doesn't overwrite the file that failed
+    /// to load.
+    fn writable(&self) -> Result<(), ConfigError> {
+        if self.config_fallback {
+            return Err(ConfigError::Fallback);
+        }
+
+        Ok(())
+    }
+
+    /// The config with the current window geometry, an off-screen position replaced by the old one.
+    fn with_window_geometry(&self) -> Config {
+        let mut config = self.config.clone();
+        config.set_window_geometry(self.window_size, self.window_position);
+        if config.window_position_on_screen().is_none() {
+            let (_, position) = self.config.window_geometry();
+            config.set_window_geometry(self.window_size, position);
+        }
 
         config
-    } else {
-        let file = std::fs::File::open(&config_path).unwrap();
-        let mut reader = std::io::BufReader::new(file);
-        let mut buf = String::new();
-        reader.read_to_string(&mut buf).unwrap();
+    }
 
-        match toml::from_str(&buf) {
-            Ok(config) => config,
+    /// What a key pressed in the window does, the arrows selecting a timer and everything else
+    /// going by the `[keys]` config.
+    fn key_message(&self, key: &Key, modifiers: iced::keyboard::Modifiers) -> Option<Message> {
+        match key {
+            Key::Named(Named::ArrowDown) => return Some(Message::SelectNext),
+            Key::Named(Named::ArrowUp) => return Some(Message::SelectPrevious),
+            _ => {}
+        }
+
+        let modifiers = Modifiers {
+            ctrl: modifiers.control(),
+            alt: modifiers.alt(),
+            shift: modifiers.shift(),
+            logo: modifiers.logo(),
+        };
+        self.config
+            .keys
+            .action_for(&key_name(key)?, modifiers)
+            .map(action_message)
+    }
+
+    /// Runs the actions of global shortcuts pressed since the last tick.
+    fn poll_hotkeys(&mut self) -> Task<Message> {
+        let Some(hotkeys) = self.hotkeys.as_mut() else {
+            return Task::none();
+        };
+
+        let actions = hotkeys.poll();
+        let tasks: Vec<Task<Message>> = actions
+            .into_iter()
+            .map(|action| self.update(action_message(action)))
+            .collect();
+        Task::batch(tasks)
+    }
+
+    /// Shows the selected timer in the tray.
+    fn update_tray(&mut self) {
+        let timer = &self.timers[self.selected];
+        let status = TrayStatus {
+            paused: timer.stopwatch.is_paused(),
+            level: timer.level(),
+            elapsed: timer.stopwatch.elapsed(),
+            label: timer.stopwatch.label().map(str::to_owned),
+        };
+
+        if let Some(tray) = self.tray.as_mut() {
+            tray.update(&status);
+        }
+    }
+
+    /// Runs the tray menu entries picked since the last tick.
+    fn poll_tray(&mut self) -> Task<Message> {
+        let Some(tray) = self.tray.as_mut() else {
+            return Task::none();
+        };
+
+        let actions = tray.poll();
+        let tasks: Vec<Task<Message>> = actions
+            .into_iter()
+            .flat_map(tray_messages)
+            .map(|message| self.update(message))
+            .collect();
+        Task::batch(tasks)
+    }
+
+    fn bind_hotkeys(&mut self) {
+        let Some(hotkeys) = self.hotkeys.as_mut() else {
+            return;
+        };
+
+        let bound = self
+            .config
+            .keys
+            .global_bindings()
+            .and_then(|bindings| hotkeys.bind(&bindings));
+        if let Err(e) = bound {
+            eprintln!("Global shortcuts disabled: {}", e);
+        }
+    }
+
+    /// Deals with the system having been suspended or the wall clock changed since the last tick.
+    ///
+    /// Setting the clock back only moves the timers along, a suspend while a timer was running is
+    /// left to the user to count as work or as a break.
+    fn check_clock(&mut self) {
+        match self.clock.tick() {
+            Some(ClockJump::Backwards(by)) => {
+                for timer in &mut self.timers {
+                    timer.rewind(by);
+                }
+            }
+            Some(ClockJump::Gap { start, end }) => {
+                if self.timers.iter().all(|timer| timer.stopwatch.is_paused()) {
+                    return;
+                }
+
+                // A second suspend before answering extends the first one.
+                let start = self.gap.map_or(start, |(earlier, _)| earlier);
+                self.gap = Some((start, end));
+            }
+            None => {}
+        }
+    }
+
+    /// Pauses the running timers if the user walked away from them.
+    fn pause_idle_timers(&mut self) {
+        if self.config.pause_when_idle_minutes == 0
+            || self.timers.iter().all(|timer| timer.stopwatch.is_paused())
+        {
+            return;
+        }
+        let Some(idle) = self.idle.as_mut() else {
+            return;
+        };
+
+        let idle_seconds = match idle.idle_seconds() {
+            Ok(seconds) => seconds,
             Err(e) => {
-                eprintln!("Failed to parse config: {}\n", e);
-                std::process::exit(1);
+                eprintln!("Idle detection disabled: {}", e);
+                self.idle = None;
+                return;
+            }
+        };
+
+        let now = SystemTime::now();
+        for timer in &mut self.timers {
+            timer.pause_if_idle(&self.config, idle_seconds, now);
+        }
+    }
+
+    fn notify_all(&mut self, alerts: Vec<Alert>) {
+        let Some(notifier) = self.notifier.as_mut() else {
+            return;
+        };
+
+        for (summary, body) in alerts {
+            if let Err(e) = notifier.notify(summary, &body) {
+                eprintln!("{}", e);
             }
         }
+    }
+
+    fn show_stats(&mut self) {
+        let timer = self.timer();
+        let stats = Stats::compute(&timer.history(), &timer.warn);
+
+        self.screen = Screen::Stats(stats);
+        self.export_status = None;
+    }
+
+    /// Exports the selected timer's history to the downloads (or home) directory.
+    fn export(&self, format: Format) -> Result<std::path::PathBuf, String> {
+        let dir = dirs::download_dir()
+            .or_else(dirs::home_dir)
+            .ok_or("No directory to export to")?;
+        let timer = self.timer();
+        let path = dir.join(format!(
+            "zarthus_stopwatch{}.{}",
+            timer
+                .name
+                .as_ref()
+                .map(|name| format!("-{}", name))
+                .unwrap_or_default(),
+            format.extension()
+        ));
+
+        let file = std::fs::File::create(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut writer = std::io::BufWriter::new(file);
+        export::export(&timer.history(), format, &mut writer)
+            .and_then(|_| std::io::Write::flush(&mut writer))
+            .map_err(|e| format!("Failed to export: {}", e))?;
+
+        Ok(path)
+    }
+}
+
+#[cfg(not(feature = "store_sessions"))]
+fn load_history(_: Option<&SessionStore>) -> Option<Vec<Session>> {
+    None
+}
+
+#[cfg(feature = "store_sessions")]
+fn load_history(store: Option<&SessionStore>) -> Option<Vec<Session>> {
+    match store?.load() {
+        Ok(history) => Some(history),
+        Err(e) => {
+            eprintln!("Failed to load session history: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "store_sessions"))]
+fn migrate_sessions() {}
+
+#[cfg(feature = "store_sessions")]
+fn migrate_sessions() {
+    match zarthus_stopwatch::paths::migrate_data_dir() {
+        Ok(0) => {}
+        Ok(count) => eprintln!("Moved {} session stores to the data directory", count),
+        Err(e) => eprintln!("Failed to move sessions to the data directory: {}", e),
+    }
+
+    let Some(store) = SessionStore::open_default(None) else {
+        return;
+    };
+
+    match zarthus_stopwatch::import::migrate_legacy_log(&store) {
+        Ok(Some(count)) => eprintln!("Imported {} sessions from the legacy log", count),
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to import legacy log: {}", e),
+    }
+}
+
+#[cfg(not(feature = "notifications"))]
+fn open_notifier() -> Option<Box<dyn Notifier>> {
+    None
+}
+
+#[cfg(feature = "notifications")]
+fn open_notifier() -> Option<Box<dyn Notifier>> {
+    match zarthus_stopwatch::notify::DbusNotifier::session() {
+        Ok(notifier) => Some(Box::new(notifier)),
+        Err(e) => {
+            eprintln!("Notifications disabled: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "idle"))]
+fn open_idle_monitor() -> Option<Box<dyn IdleMonitor>> {
+    None
+}
+
+#[cfg(feature = "idle")]
+fn open_idle_monitor() -> Option<Box<dyn IdleMonitor>> {
+    match zarthus_stopwatch::idle::DbusIdleMonitor::session() {
+        Ok(monitor) => Some(Box::new(monitor)),
+        Err(e) => {
+            eprintln!("Idle detection disabled: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "global_hotkeys"))]
+fn open_hotkeys() -> Option<Box<dyn GlobalHotkeys>> {
+    None
+}
+
+#[cfg(feature = "global_hotkeys")]
+fn open_hotkeys() -> Option<Box<dyn GlobalHotkeys>> {
+    match zarthus_stopwatch::keys::PortalHotkeys::session() {
+        Ok(hotkeys) => Some(Box::new(hotkeys)),
+        Err(e) => {
+            eprintln!("Global shortcuts disabled: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "store_sessions"))]
+fn unstore_session(_: Option<&SessionStore>, _: &Session) {}
+
+#[cfg(feature = "store_sessions")]
+fn unstore_session(store: Option<&SessionStore>, session: &Session) {
+    let Some(store) = store else {
+        return;
     };
 
-    config
+    match store.remove(session) {
+        Ok(true) => {}
+        Ok(false) => eprintln!(
+            "Failed to remove session: not in {}",
+            store.path().display()
+        ),
+        Err(e) => eprintln!("Failed to remove session: {}", e),
+    }
 }
 
 #[cfg(not(feature = "store_sessions"))]
-fn store_sessions(_: &Vec<Session>) -> Result<(), String> {
-    Ok(())
+fn unstore_sessions(_: Option<&SessionStore>, _: impl Fn(&Session) -> bool) {}
+
+/// Removes the stored sessions `keep` returns false for.
+#[cfg(feature = "store_sessions")]
+fn unstore_sessions(store: Option<&SessionStore>, keep: impl Fn(&Session) -> bool) {
+    let Some(store) = store else {
+        return;
+    };
+
+    if let Err(e) = store.retain(keep) {
+        eprintln!("Failed to remove sessions: {}", e);
+    }
+}
+
+#[cfg(not(all(feature = "tray", target_os = "linux")))]
+fn open_tray() -> Option<Box<dyn TrayIcon>> {
+    None
+}
+
+#[cfg(all(feature = "tray", target_os = "linux"))]
+fn open_tray() -> Option<Box<dyn TrayIcon>> {
+    match zarthus_stopwatch::tray::SniTray::spawn() {
+        Ok(tray) => Some(Box::new(tray)),
+        Err(e) => {
+            eprintln!("Tray icon disabled: {}", e);
+            None
+        }
+    }
 }
 
+#[cfg(not(feature = "store_sessions"))]
+fn store_session(_: Option<&SessionStore>, _: &Session) {}
+
 #[cfg(feature = "store_sessions")]
-fn store_sessions(sessions: &Vec<Session>) -> Result<(), String> {
-    let sessions_path = dirs::config_dir().unwrap().join("zarthus_counter.log");
+fn store_session(store: Option<&SessionStore>, session: &Session) {
+    let Some(store) = store else {
+        eprintln!("Failed to store session: no data directory");
+        return;
+    };
+
+    match store.append(session) {
+        Ok(_) => {}
+        Err(e) => eprintln!("Failed to store session: {}", e),
+    }
+}
+
+fn label_input_id() -> text_input::Id {
+    text_input::Id::new("label")
+}
+
+/// Name of a key as used in key bindings, like `s` or `space`.
+fn key_name(key: &Key) -> Option<String> {
+    match key {
+        Key::Character(c) => Some(c.to_lowercase()),
+        Key::Named(named) => Some(format!("{:?}", named).to_lowercase()),
+        Key::Unidentified => None,
+    }
+}
+
+fn action_message(action: Action) -> Message {
+    match action {
+        Action::Toggle => Message::Toggle,
+        Action::Reset => Message::Reset,
+        Action::Undo => Message::Undo,
+        Action::Stats => Message::ShowStats,
+        Action::Label => Message::EditLabel,
+    }
+}
+
+/// Screens opened from the tray also bring the window back.
+fn tray_messages(action: TrayAction) -> Vec<Message> {
+    match action {
+        TrayAction::Show => vec![Message::ShowWindow],
+        TrayAction::Toggle => vec![Message::Toggle],
+        TrayAction::Reset => vec![Message::Reset, Message::ShowWindow],
+        TrayAction::Stats => vec![Message::ShowStats, Message::ShowWindow],
+        TrayAction::Settings => vec![Message::ShowSettings, Message::ShowWindow],
+        TrayAction::Quit => vec![Message::Quit],
+    }
+}
+
+/// One line per problem with the config, `None` if it loaded cleanly.
+fn config_warning(errors: &[ConfigError]) -> Option<String> {
+    if errors.is_empty() {
+        return None;
+    }
 
-    let sessdata = sessions
+    let lines: Vec<String> = errors
         .iter()
-        .map(|sess| {
-            format!(
-                "{} {}",
-                format_text(sess.end - sess.start, true),
-                if sess.pause { "pause" } else { "active" }
-            )
+        .map(|e| {
+            if e.is_warning() {
+                e.to_string()
+            } else {
+                format!("{}, using the default config", e)
+            }
         })
-        .collect::<Vec<String>>()
-        .join("\n");
+        .collect();
+
+    Some(lines.join("\n"))
+}
+
+/// The saved window position if the window ends up on the primary monitor, else the middle of it.
+fn opening_position(window: iced::Size, monitor: iced::Size) -> iced::Point {
+    let saved = OPENING_CONFIG
+        .get()
+        .and_then(|config| config.window_position_on_monitor([monitor.width, monitor.height]));
+
+    match saved {
+        Some(position) => iced::Point::from(position),
+        None => iced::Point::new(
+            (monitor.width - window.width) / 2.,
+            (monitor.height - window.height) / 2.,
+        ),
+    }
+}
+
+fn window_level(always_on_top: bool) -> window::Level {
+    if always_on_top {
+        window::Level::AlwaysOnTop
+    } else {
+        window::Level::Normal
+    }
+}
 
-    let file = std::fs::File::create(sessions_path)
-        .map_err(|e| format!("Failed to create file: {}", e))?;
-    let mut writer = std::io::BufWriter::new(file);
+#[inline]
+fn level_col(level: Level) -> iced::Color {
+    match level {
+        Level::Off => iced::Color::from_rgb8(0, 0, 0),
+        Level::Danger => iced::Color::from_rgb8(255, 0, 0),
+        Level::Warn => iced::Color::from_rgb8(255, 255, 0),
+        Level::Ok => iced::Color::from_rgb8(0, 255, 0),
+    }
+}
 
-    writer
-        .write_all(sessdata.as_bytes())
-        .map_err(|e| format!("Failed to write to file: {}", e))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(())
+    #[test]
+    fn tray_actions_map_to_messages() {
+        for (action, messages) in [
+            (TrayAction::Show, "[ShowWindow]"),
+            (TrayAction::Toggle, "[Toggle]"),
+            (TrayAction::Reset, "[Reset, ShowWindow]"),
+            (TrayAction::Stats, "[ShowStats, ShowWindow]"),
+            (TrayAction::Settings, "[ShowSettings, ShowWindow]"),
+            (TrayAction::Quit, "[Quit]"),
+        ] {
+            assert_eq!(format!("{:?}", tray_messages(action)), messages);
+        }
+    }
 }
@@ -0,0 +1,294 @@
+use std::time::{Duration, SystemTime};
+
+use crate::{format_text, Level, Stopwatch, WarnSettings};
+
+/// Something that can show a message to the user outside of the window.
+pub trait Notifier: std::fmt::Debug {
+    fn notify(&mut self, summary: &str, body: &str) -> Result<(), String>;
+}
+
+/// Keeps every notification in memory instead of showing it.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MemoryNotifier {
+    pub sent: Vec<(String, String)>,
+}
+
+#[cfg(test)]
+impl Notifier for MemoryNotifier {
+    fn notify(&mut self, summary: &str, body: &str) -> Result<(), String> {
+        self.sent.push((summary.to_owned(), body.to_owned()));
+        Ok(())
+    }
+}
+
+/// Sends notifications over D-Bus following the freedesktop notification spec.
+#[cfg(feature = "notifications")]
+#[derive(Debug)]
+pub struct DbusNotifier {
+    connection: zbus::blocking::Connection,
+}
+
+#[cfg(feature = "notifications")]
+impl DbusNotifier {
+    /// Connects to the user's session bus.
+    pub fn session() -> Result<Self, String> {
+        let connection = zbus::blocking::Connection::session()
+            .map_err(|e| format!("Failed to connect to session bus: {}", e))?;
+
+        Ok(Self { connection })
+    }
+}
+
+#[cfg(feature = "notifications")]
+impl Notifier for DbusNotifier {
+    fn notify(&mut self, summary: &str, body: &str) -> Result<(), String> {
+        let actions: Vec<&str> = vec![];
+        let hints: std::collections::HashMap<&str, zbus::zvariant::Value> = Default::default();
+
+        self.connection
+            .call_method(
+                Some("org.freedesktop.Notifications"),
+                "/org/freedesktop/Notifications",
+                Some("org.freedesktop.Notifications"),
+                "Notify",
+                &("Stopwatch", 0u32, "", summary, body, actions, hints, -1i32),
+            )
+            .map_err(|e| format!("Failed to send notification: {}", e))?;
+
+        Ok(())
+    }
+}
+
+/// Reports each threshold crossing of an active stretch once.
+#[derive(Debug, Default)]
+pub struct ThresholdAlerts {
+    start: Option<SystemTime>,
+    fired: Option<Level>,
+}
+
+impl ThresholdAlerts {
+    /// Returns the level that was newly reached since the last check, if any.
+    pub fn check(&mut self, stopwatch: &Stopwatch, warn: &WarnSettings) -> Option<Level> {
+        if stopwatch.is_paused() {
+            self.start = None;
+            return None;
+        }
+
+        if self.start != Some(stopwatch.start()) {
+            self.start = Some(stopwatch.start());
+            self.fired = None;
+        }
+
+        let level = warn.level(stopwatch.elapsed());
+        if level < Level::Warn || self.fired.is_some_and(|fired| fired >= level) {
+            return None;
+        }
+
+        self.fired = Some(level);
+        Some(level)
+    }
+
+    /// Keeps up with [`Stopwatch::rewind_start`], so levels already reported stay reported.
+    pub fn rewind(&mut self, by: Duration) {
+        self.start = self
+            .start
+            .map(|start| start.checked_sub(by).unwrap_or(start));
+    }
+}
+
+/// Summary and body of the notification for reaching `level` after `seconds` of activity.
+pub fn threshold_message(level: Level, seconds: u64) -> (&'static str, String) {
+    let summary = match level {
+        Level::Danger => "Take a break now",
+        _ => "Time for a break",
+    };
+
+    (
+        summary,
+        format!("Active for {}", format_text(seconds, false)),
+    )
+}
+
+/// Summary and body of the notification for the stopwatch switching into an interval of
+/// `seconds`.
+pub fn interval_message(paused: bool, seconds: u64) -> (&'static str, String) {
+    if paused {
+        (
+            "Break time",
+            format!("Back to work in {}", format_text(seconds, false)),
+        )
+    } else {
+        (
+            "Back to work",
+            format!("Next break in {}", format_text(seconds, false)),
+        )
+    }
+}
+
+/// Repeats the break reminder every `every` seconds once an active stretch is past the danger
+/// threshold, unless it is snoozed.
+#[derive(Debug)]
+pub struct Reminders {
+    every: u64,
+    start: Option<SystemTime>,
+    next: u64,
+    snoozed: Option<(u16, SystemTime)>,
+}
+
+impl Reminders {
+    pub fn new(every_minutes: u16) -> Self {
+        Self {
+            every: every_minutes as u64 * 60,
+            start: None,
+            next: 0,
+            snoozed: None,
+        }
+    }
+
+    /// Whether a reminder is due now. Reminders are off if `every` or the danger threshold is 0.
+    pub fn check(&mut self, stopwatch: &Stopwatch, warn: &WarnSettings) -> bool {
+        if stopwatch.is_paused() || self.every == 0 || warn.danger_after == 0 {
+            self.start = None;
+            self.snoozed = None;
+            return false;
+        }
+
+        if self.start != Some(stopwatch.start()) {
+            self.start = Some(stopwatch.start());
+            self.next = warn.danger_after + self.every;
+            self.snoozed = None;
+        }
+
+        let elapsed = stopwatch.elapsed();
+        if self.snoozed.is_some() || elapsed < self.next {
+            return false;
+        }
+
+        self.next = elapsed + self.every;
+        true
+    }
+
+    /// Holds off reminders until [`Reminders::wake`] is called, `minutes` being how long the
+    /// caller intends to wait.
+    pub fn snooze(&mut self, stopwatch: &mut Stopwatch, minutes: u16) {
+        self.snoozed = Some((minutes, SystemTime::now()));
+        stopwatch.snooze();
+    }
+
+    /// Minutes the current snooze was asked for.
+    pub fn snoozed(&self) -> Option<u16> {
+        self.snoozed.map(|(minutes, _)| minutes)
+    }
+
+    /// Whether the snooze has lasted the minutes asked for by `now`, give or take a second.
+    pub fn snooze_over(&self, now: SystemTime) -> bool {
+        self.snoozed.is_some_and(|(minutes, since)| {
+            now + Duration::from_secs(1) >= since + Duration::from_secs(minutes as u64 * 60)
+        })
+    }
+
+    /// Keeps up with [`Stopwatch::rewind_start`], the snooze lasting as long as it was asked for.
+    pub fn rewind(&mut self, by: Duration) {
+        self.start = self
+            .start
+            .map(|start| start.checked_sub(by).unwrap_or(start));
+        self.snoozed = self
+            .snoozed
+            .map(|(minutes, since)| (minutes, since.checked_sub(by).unwrap_or(since)));
+    }
+
+    /// Ends the snooze, making a reminder due on the next check.
+    pub fn wake(&mut self, stopwatch: &Stopwatch) {
+        self.snoozed = None;
+        self.next = stopwatch.elapsed();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notify_thresholds(
+        alerts: &mut ThresholdAlerts,
+        stopwatch: &Stopwatch,
+        warn: &WarnSettings,
+        notifier: &mut dyn Notifier,
+    ) {
+        if let Some(level) = alerts.check(stopwatch, warn) {
+            let (summary, body) = threshold_message(level, stopwatch.elapsed());
+            notifier.notify(summary, &body).unwrap();
+        }
+    }
+
+    #[test]
+    fn each_threshold_is_notified_once() {
+        let warn = WarnSettings::from_minutes(30, 60);
+        let mut alerts = ThresholdAlerts::default();
+        let mut notifier = MemoryNotifier::default();
+        let now = SystemTime::now();
+        let warned = Stopwatch::new_at(false, now - Duration::from_secs(45 * 60));
+        let overdue = Stopwatch::new_at(false, now - Duration::from_secs(65 * 60));
+
+        for stopwatch in [&warned, &warned, &warned, &overdue, &overdue] {
+            notify_thresholds(&mut alerts, stopwatch, &warn, &mut notifier);
+        }
+
+        let summaries: Vec<&str> = notifier.sent.iter().map(|(s, _)| s.as_str()).collect();
+        assert_eq!(summaries, ["Time for a break", "Take a break now"]);
+    }
+
+    #[test]
+    fn setting_the_clock_back_does_not_notify_again() {
+        let warn = WarnSettings::from_minutes(30, 60);
+        let mut alerts = ThresholdAlerts::default();
+        let mut stopwatch =
+            Stopwatch::new_at(false, SystemTime::now() - Duration::from_secs(45 * 60));
+        assert_eq!(alerts.check(&stopwatch, &warn), Some(Level::Warn));
+
+        let by = Duration::from_secs(5 * 60);
+        stopwatch.rewind_start(by);
+        alerts.rewind(by);
+
+        assert_eq!(alerts.check(&stopwatch, &warn), None);
+    }
+
+    #[test]
+    fn paused_stopwatch_is_not_notified() {
+        let warn = WarnSettings::from_minutes(1, 2);
+        let mut alerts = ThresholdAlerts::default();
+        let stopwatch = Stopwatch::new_at(true, SystemTime::now() - Duration::from_secs(600));
+
+        assert_eq!(alerts.check(&stopwatch, &warn), None);
+    }
+
+    #[test]
+    fn reminders_repeat_past_danger_until_snoozed() {
+        let warn = WarnSettings::from_minutes(30, 60);
+        let mut reminders = Reminders::new(5);
+        let mut stopwatch =
+            Stopwatch::new_at(false, SystemTime::now() - Duration::from_secs(66 * 60));
+
+        assert!(reminders.check(&stopwatch, &warn));
+        assert!(!reminders.check(&stopwatch, &warn));
+
+        reminders.snooze(&mut stopwatch, 10);
+        assert!(!reminders.check(&stopwatch, &warn));
+        assert_eq!(reminders.snoozed(), Some(10));
+        reminders.wake(&stopwatch);
+        assert!(reminders.check(&stopwatch, &warn));
+        assert_eq!(stopwatch.current().snoozes, 1);
+    }
+
+    #[test]
+    fn snooze_is_over_once_its_minutes_have_passed() {
+        let mut reminders = Reminders::new(5);
+        let mut stopwatch = Stopwatch::new(false);
+        let now = SystemTime::now();
+
+        assert!(!reminders.snooze_over(now));
+        reminders.snooze(&mut stopwatch, 10);
+        assert!(!reminders.snooze_over(now + Duration::from_secs(9 * 60)));
+        assert!(reminders.snooze_over(now + Duration::from_secs(10 * 60)));
+    }
+}
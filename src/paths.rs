@@ -0,0 +1,235 @@
+//! Where the config file and the session data live.
+//!
+//! By default the config is kept in the platform's config directory and the sessions in its data
+//! directory. `STOPWATCH_HOME` puts both into one directory, for a portable install or a separate
+//! profile, and paths given on the command line take precedence over either.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Environment variable naming a directory to keep the config and the sessions in.
+pub const HOME_VAR: &str = "STOPWATCH_HOME";
+
+const CONFIG_FILE: &str = "zarthus_counter.toml";
+
+static OVERRIDES: OnceLock<Overrides> = OnceLock::new();
+
+#[derive(Debug)]
+struct Overrides {
+    config: Option<PathBuf>,
+    data_dir: Option<PathBuf>,
+}
+
+/// Uses `config` as the config file and `data_dir` for the sessions, where given.
+///
+/// Only the first call has an effect, so this has to happen before anything is read.
+pub fn set_overrides(config: Option<PathBuf>, data_dir: Option<PathBuf>) {
+    if OVERRIDES.set(Overrides { config, data_dir }).is_err() {
+        eprintln!("Paths were already set, ignoring the new ones");
+    }
+}
+
+fn home() -> Option<PathBuf> {
+    home_from(std::env::var_os(HOME_VAR))
+}
+
+/// `STOPWATCH_HOME` set to `value`, an empty one counting as unset.
+fn home_from(value: Option<std::ffi::OsString>) -> Option<PathBuf> {
+    value.filter(|home| !home.is_empty()).map(PathBuf::from)
+}
+
+/// The config file, `None` if the platform has no config directory.
+pub fn config_file() -> Option<PathBuf> {
+    let config = OVERRIDES.get().and_then(|o| o.config.clone());
+
+    choose_config_file(config, home(), dirs::config_dir())
+}
+
+/// The file given on the command line, else the one in `home`, else the one in `config_dir`.
+fn choose_config_file(
+    config: Option<PathBuf>,
+    home: Option<PathBuf>,
+    config_dir: Option<PathBuf>,
+) -> Option<PathBuf> {
+    config.or_else(|| home.or(config_dir).map(|dir| dir.join(CONFIG_FILE)))
+}
+
+/// The directory holding the session stores, `None` if the platform has no data directory.
+pub fn data_dir() -> Option<PathBuf> {
+    let data_dir = OVERRIDES.get().and_then(|o| o.data_dir.clone());
+
+    choose_data_dir(data_dir, home(), dirs::data_dir())
+}
+
+/// The directory given on the command line, else `home`, else the platform's `data_dir`.
+fn choose_data_dir(
+    data_dir: Option<PathBuf>,
+    home: Option<PathBuf>,
+    platform: Option<PathBuf>,
+) -> Option<PathBuf> {
+    data_dir.or(home).or(platform)
+}
+
+/// Whether the sessions are kept in the platform's data directory, rather than one that was set.
+pub fn is_default_data_dir() -> bool {
+    home().is_none() && !OVERRIDES.get().is_some_and(|o| o.data_dir.is_some())
+}
+
+/// Moves session stores that earlier versions kept in the config directory to the data directory.
+///
+/// Only the default locations are migrated, a store already in the data directory is never
+/// overwritten. Returns how many stores were moved.
+pub fn migrate_data_dir() -> Result<usize, String> {
+    if !is_default_data_dir() {
+        return Ok(0);
+    }
+    let (Some(from), Some(to)) = (dirs::config_dir(), dirs::data_dir()) else {
+        return Ok(0);
+    };
+    if from == to {
+        return Ok(0);
+    }
+
+    move_stores(&from, &to)
+}
+
+/// Moves the session stores in `from` to `to`, skipping those that `to` already has.
+fn move_stores(from: &Path, to: &Path) -> Result<usize, String> {
+    let entries = match std::fs::read_dir(from) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(format!("Failed to read {}: {}", from.display(), e)),
+    };
+
+    let mut moved = 0;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+        let name = entry.file_name();
+        let is_store = name.to_str().is_some_and(|name| {
+            name.starts_with("zarthus_counter.sessions.") && name.ends_with(".jsonl")
+        });
+        if !is_store {
+            continue;
+        }
+
+        let target = to.join(&name);
+        if target.exists() {
+            eprintln!(
+                "Not moving {}, {} already exists",
+                entry.path().display(),
+                target.display()
+            );
+            continue;
+        }
+
+        std::fs::create_dir_all(to)
+            .map_err(|e| format!("Failed to create {}: {}", to.display(), e))?;
+        move_file(&entry.path(), &target).map_err(|e| {
+            format!(
+                "Failed to move {} to {}: {}",
+                entry.path().display(),
+                target.display(),
+                e
+            )
+        })?;
+        moved += 1;
+    }
+
+    Ok(moved)
+}
+
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    // Renaming fails across file systems, which the config and data directories may be on.
+    std::fs::copy(from, to)?;
+    std::fs::remove_file(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "zarthus_stopwatch_test_{}_{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn flags_come_before_the_home_and_the_home_before_the_platform() {
+        let some = |path: &str| Some(PathBuf::from(path));
+
+        for (flag, home, platform, expected) in [
+            (some("a.toml"), some("home"), some("config"), "a.toml"),
+            (
+                None,
+                some("home"),
+                some("config"),
+                "home/zarthus_counter.toml",
+            ),
+            (None, None, some("config"), "config/zarthus_counter.toml"),
+        ] {
+            assert_eq!(choose_config_file(flag, home, platform), some(expected));
+        }
+        assert_eq!(choose_config_file(None, None, None), None);
+
+        for (flag, home, platform, expected) in [
+            (some("data"), some("home"), some("platform"), "data"),
+            (None, some("home"), some("platform"), "home"),
+            (None, None, some("platform"), "platform"),
+        ] {
+            assert_eq!(choose_data_dir(flag, home, platform), some(expected));
+        }
+        assert_eq!(choose_data_dir(None, None, None), None);
+    }
+
+    #[test]
+    fn empty_home_counts_as_unset() {
+        assert_eq!(home_from(None), None);
+        assert_eq!(home_from(Some("".into())), None);
+        assert_eq!(
+            home_from(Some("portable".into())),
+            Some(PathBuf::from("portable"))
+        );
+    }
+
+    #[test]
+    fn stores_are_moved_unless_the_target_exists() {
+        let from = temp_dir("migrate_from");
+        let to = temp_dir("migrate_to");
+        std::fs::write(from.join("zarthus_counter.sessions.jsonl"), "old\n").unwrap();
+        std::fs::write(
+            from.join("zarthus_counter.sessions.work.jsonl"),
+            "old work\n",
+        )
+        .unwrap();
+        std::fs::write(from.join(CONFIG_FILE), "").unwrap();
+        std::fs::write(to.join("zarthus_counter.sessions.work.jsonl"), "new work\n").unwrap();
+
+        assert_eq!(move_stores(&from, &to), Ok(1));
+
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(to.join("zarthus_counter.sessions.jsonl")), "old\n");
+        assert_eq!(
+            read(to.join("zarthus_counter.sessions.work.jsonl")),
+            "new work\n"
+        );
+        assert!(!from.join("zarthus_counter.sessions.jsonl").exists());
+        assert!(from.join("zarthus_counter.sessions.work.jsonl").exists());
+        assert!(from.join(CONFIG_FILE).exists());
+        assert_eq!(move_stores(&from.join("missing"), &to), Ok(0));
+
+        for dir in [from, to] {
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+    }
+}
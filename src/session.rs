@@ -0,0 +1,497 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A finished stretch of either activity or pause, in seconds since the unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub pause: bool,
+    pub start: u64,
+    pub end: u64,
+    pub label: Option<String>,
+    /// How often a break reminder was snoozed during this session.
+    pub snoozes: u32,
+    /// Ended by the app rather than by hand: the interval plan, being idle or a suspend.
+    pub automatic: bool,
+    /// The stopwatch was reset when this session ended, which ends its run.
+    pub reset: bool,
+}
+
+impl Session {
+    pub fn new(pause: bool, start: SystemTime, end: SystemTime) -> Self {
+        Self {
+            pause,
+            start: unix_secs(start),
+            end: unix_secs(end),
+            label: None,
+            snoozes: 0,
+            automatic: false,
+            reset: false,
+        }
+    }
+
+    /// An empty session ending the run at `at`, for when the sessions of that run were removed.
+    ///
+    /// [`crate::Stopwatch::resume`] starts over paused after it, and being active and empty it
+    /// adds nothing to the stats.
+    pub fn run_boundary(at: SystemTime) -> Self {
+        Self {
+            reset: true,
+            ..Self::new(false, at, at)
+        }
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// Whether both cover the same time the same way, whatever else was recorded about them.
+    pub fn same_stretch(&self, other: &Session) -> bool {
+        self.pause == other.pause && self.start == other.start && self.end == other.end
+    }
+}
+
+/// Distinct labels of `sessions`, most recently used first.
+pub fn recent_labels(sessions: &[Session], limit: usize) -> Vec<String> {
+    let mut labels: Vec<String> = vec![];
+
+    for label in sessions.iter().rev().filter_map(|s| s.label.as_ref()) {
+        if labels.len() >= limit {
+            break;
+        }
+        if !labels.contains(label) {
+            labels.push(label.clone());
+        }
+    }
+
+    labels
+}
+
+/// Seconds since the unix epoch, clamped to zero for times before it.
+pub fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Version written into every record, bumped whenever an existing field changes meaning or type.
+///
+/// Adding an optional field doesn't count: older versions ignore it and newer ones default it
+/// when reading records without it.
+pub const RECORD_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    Active,
+    Pause,
+}
+
+/// On-disk representation of a [`Session`], one JSON object per line.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct Record {
+    v: u32,
+    start: u64,
+    end: u64,
+    kind: Kind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    snoozes: u32,
+    #[serde(default, skip_serializing_if = "is_false")]
+    automatic: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    reset: bool,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+impl From<&Session> for Record {
+    fn from(session: &Session) -> Self {
+        Self {
+            v: RECORD_VERSION,
+            start: session.start,
+            end: session.end,
+            kind: if session.pause {
+                Kind::Pause
+            } else {
+                Kind::Active
+            },
+            label: session.label.clone(),
+            snoozes: session.snoozes,
+            automatic: session.automatic,
+            reset: session.reset,
+        }
+    }
+}
+
+impl From<Record> for Session {
+    fn from(record: Record) -> Self {
+        Self {
+            pause: record.kind == Kind::Pause,
+            start: record.start,
+            end: record.end,
+            label: record.label,
+            snoozes: record.snoozes,
+            automatic: record.automatic,
+            reset: record.reset,
+        }
+    }
+}
+
+/// Append-only history of sessions, stored as JSON Lines.
+///
+/// Each session is written as a single line and synced, so a crash can at most leave one torn
+/// line at the end of the file. Such a line is skipped on load and terminated before the next
+/// append.
+#[derive(Debug, Clone)]
+pub struct SessionStore {
+    path: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The store in the data directory, `None` if there is none.
+    ///
+    /// Each profile keeps its stores in a directory of its own.
+    pub fn open_default(profile: Option<&str>) -> Option<Self> {
+        store_dir(profile).map(|dir| Self::new(dir.join("zarthus_counter.sessions.jsonl")))
+    }
+
+    /// The store of an additional stopwatch called `name`, next to the default one.
+    pub fn open_named(profile: Option<&str>, name: &str) -> Option<Self> {
+        store_dir(profile).map(|dir| {
+            Self::new(dir.join(format!("zarthus_counter.sessions.{}.jsonl", slug(name))))
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, session: &Session) -> Result<(), String> {
+        self.append_all(std::slice::from_ref(session))
+    }
+
+    pub fn append_all(&self, sessions: &[Session]) -> Result<(), String> {
+        let mut data = String::new();
+        for session in sessions {
+            let line = serde_json::to_string(&Record::from(session))
+                .map_err(|e| format!("Failed to serialize session: {}", e))?;
+            data.push_str(&line);
+            data.push('\n');
+        }
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open {}: {}", self.path.display(), e))?;
+
+        if !ends_with_newline(&mut file)? {
+            data.insert(0, '\n');
+        }
+
+        file.write_all(data.as_bytes())
+            .map_err(|e| format!("Failed to write to file: {}", e))?;
+        file.sync_data()
+            .map_err(|e| format!("Failed to sync file: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Adds sessions from elsewhere, keeping the history ordered by start. Returns how many
+    /// were added, sessions already in the store being skipped so merging twice changes nothing.
+    ///
+    /// Unlike [`SessionStore::append`] this rewrites the store, through a temporary file that
+    /// replaces it once fully written.
+    pub fn merge(&self, sessions: &[Session]) -> Result<usize, String> {
+        let mut merged = self.load()?;
+        let new: Vec<Session> = sessions
+            .iter()
+            .filter(|session| !merged.iter().any(|stored| stored.same_stretch(session)))
+            .cloned()
+            .collect();
+        if new.is_empty() {
+            return Ok(0);
+        }
+
+        merged.extend_from_slice(&new);
+        merged.sort_by_key(|session| session.start);
+
+        self.replace(&merged)?;
+        Ok(new.len())
+    }
+
+    /// Removes the last stored session equal to `session`, returning whether there was one.
+    ///
+    /// Rewrites the store like [`SessionStore::merge`].
+    pub fn remove(&self, session: &Session) -> Result<bool, String> {
+        let mut sessions = self.load()?;
+        let Some(index) = sessions.iter().rposition(|stored| stored == session) else {
+            return Ok(false);
+        };
+        sessions.remove(index);
+
+        self.replace(&sessions)?;
+        Ok(true)
+    }
+
+    /// Keeps only the sessions `keep` returns true for, returning how many were removed.
+    ///
+    /// Rewrites the store like [`SessionStore::merge`], unless nothing is removed.
+    pub fn retain(&self, keep: impl Fn(&Session) -> bool) -> Result<usize, String> {
+        let mut sessions = self.load()?;
+        let before = sessions.len();
+        sessions.retain(|session| keep(session));
+        let removed = before - sessions.len();
+        if removed == 0 {
+            return Ok(0);
+        }
+
+        self.replace(&sessions)?;
+        Ok(removed)
+    }
+
+    /// Writes `sessions` to a temporary file, which then replaces the store.
+    fn replace(&self, sessions: &[Session]) -> Result<(), String> {
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        let tmp = Self::new(&tmp_path);
+        if tmp_path.exists() {
+            std::fs::remove_file(&tmp_path)
+                .map_err(|e| format!("Failed to remove {}: {}", tmp_path.display(), e))?;
+        }
+        tmp.append_all(sessions)?;
+
+        std::fs::rename(&tmp_path, &self.path)
+            .map_err(|e| format!("Failed to replace {}: {}", self.path.display(), e))
+    }
+
+    /// Reads the full history, oldest first. A missing file is an empty history.
+    pub fn load(&self) -> Result<Vec<Session>, String> {
+        let buf = match std::fs::read_to_string(&self.path) {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(format!("Failed to read {}: {}", self.path.display(), e)),
+        };
+
+        let mut sessions = vec![];
+        for (no, line) in buf.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<Record>(line) {
+                Ok(record) if record.v <= RECORD_VERSION => sessions.push(record.into()),
+                Ok(record) => eprintln!(
+                    "Skipping session on line {}: unsupported version {}",
+                    no + 1,
+                    record.v
+                ),
+                Err(e) => eprintln!("Skipping session on line {}: {}", no + 1, e),
+            }
+        }
+
+        Ok(sessions)
+    }
+}
+
+fn store_dir(profile: Option<&str>) -> Option<PathBuf> {
+    let dir = crate::paths::data_dir()?;
+
+    Some(match profile {
+        Some(profile) => dir.join("profiles").join(slug(profile)),
+        None => dir,
+    })
+}
+
+/// `name` reduced to characters that are safe in a file name.
+fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn ends_with_newline(file: &mut std::fs::File) -> Result<bool, String> {
+    let len = file
+        .metadata()
+        .map_err(|e| format!("Failed to read metadata: {}", e))?
+        .len();
+    if len == 0 {
+        return Ok(true);
+    }
+
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::Start(len - 1))
+        .and_then(|_| file.read_exact(&mut last))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    Ok(last[0] == b'\n')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_store(name: &str) -> SessionStore {
+        let path = std::env::temp_dir().join(format!(
+            "zarthus_stopwatch_test_{}_{}.jsonl",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        SessionStore::new(path)
+    }
+
+    fn session(pause: bool, start: u64, end: u64) -> Session {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+
+        Session::new(pause, at(start), at(end))
+    }
+
+    #[test]
+    fn appended_sessions_load_back() {
+        let store = temp_store("append");
+        let labelled = Session {
+            label: Some("review".to_owned()),
+            snoozes: 2,
+            ..session(false, 10, 20)
+        };
+
+        store.append(&labelled).unwrap();
+        store.append(&session(true, 20, 30)).unwrap();
+
+        assert_eq!(store.load().unwrap(), [labelled, session(true, 20, 30)]);
+        std::fs::remove_file(store.path()).unwrap();
+    }
+
+    #[test]
+    fn torn_line_is_skipped_and_terminated() {
+        let store = temp_store("torn");
+        std::fs::write(
+            store.path(),
+            "{\"v\":1,\"start\":0,\"end\":5,\"kind\":\"act",
+        )
+        .unwrap();
+
+        store.append(&session(true, 5, 10)).unwrap();
+
+        assert_eq!(store.load().unwrap(), [session(true, 5, 10)]);
+        std::fs::remove_file(store.path()).unwrap();
+    }
+
+    #[test]
+    fn merged_sessions_are_ordered_by_start() {
+        let store = temp_store("merge_order");
+        store.append(&session(false, 20, 30)).unwrap();
+
+        store
+            .merge(&[session(true, 10, 20), session(false, 0, 10)])
+            .unwrap();
+
+        assert_eq!(
+            store.load().unwrap(),
+            [
+                session(false, 0, 10),
+                session(true, 10, 20),
+                session(false, 20, 30)
+            ]
+        );
+        std::fs::remove_file(store.path()).unwrap();
+    }
+
+    #[test]
+    fn merging_twice_adds_nothing_the_second_time() {
+        let store = temp_store("merge");
+        store.append(&session(false, 0, 10)).unwrap();
+        let imported = [session(true, 10, 20), session(false, 0, 10)];
+
+        assert_eq!(store.merge(&imported).unwrap(), 1);
+        assert_eq!(store.merge(&imported).unwrap(), 0);
+        assert_eq!(
+            store.load().unwrap(),
+            [session(false, 0, 10), session(true, 10, 20)]
+        );
+        std::fs::remove_file(store.path()).unwrap();
+    }
+
+    #[test]
+    fn removes_only_what_is_asked_for() {
+        let store = temp_store("remove");
+        for stored in [
+            session(false, 0, 10),
+            session(true, 10, 20),
+            session(false, 20, 30),
+        ] {
+            store.append(&stored).unwrap();
+        }
+
+        assert!(store.remove(&session(true, 10, 20)).unwrap());
+        assert!(!store.remove(&session(true, 10, 20)).unwrap());
+        assert_eq!(store.retain(|stored| stored.start < 20).unwrap(), 1);
+        assert_eq!(store.load().unwrap(), [session(false, 0, 10)]);
+        std::fs::remove_file(store.path()).unwrap();
+    }
+
+    #[test]
+    fn history_cleared_up_to_a_run_boundary_resumes_paused() {
+        let store = temp_store("boundary");
+        for stored in [
+            session(false, 0, 10),
+            session(true, 10, 20),
+            session(false, 100, 110),
+        ] {
+            store.append(&stored).unwrap();
+        }
+
+        store.retain(|stored| stored.start < 100).unwrap();
+        let now = UNIX_EPOCH + Duration::from_secs(120);
+        store.append(&Session::run_boundary(now)).unwrap();
+
+        let history = store.load().unwrap();
+        let stopwatch = crate::Stopwatch::resume(&history).unwrap();
+        assert!(stopwatch.is_paused());
+        assert_eq!(stopwatch.start(), now);
+        assert!(stopwatch.sessions().is_empty());
+        let warn = crate::WarnSettings::from_minutes(45, 60);
+        assert_eq!(
+            crate::Stats::compute(&history, &warn).total,
+            crate::Stats::compute(&history[..2], &warn).total
+        );
+        std::fs::remove_file(store.path()).unwrap();
+    }
+
+    #[test]
+    fn recent_labels_are_distinct_and_newest_first() {
+        let labelled = |label: &str| Session {
+            label: Some(label.to_owned()),
+            ..session(false, 0, 1)
+        };
+        let sessions = [labelled("a"), labelled("b"), labelled("a"), labelled("c")];
+
+        assert_eq!(recent_labels(&sessions, 2), ["c", "a"]);
+    }
+}
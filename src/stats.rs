@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, TimeZone};
+
+use crate::{Session, WarnSettings};
+
+/// Aggregates over a set of sessions, in seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Totals {
+    pub active: u64,
+    pub pause: u64,
+    /// Longest single active session.
+    pub longest_active: u64,
+    /// Active sessions that ran past the danger threshold.
+    pub danger_exceeded: usize,
+    pub breaks: usize,
+    /// Break reminders that were postponed.
+    pub snoozes: u32,
+}
+
+impl Totals {
+    fn add(&mut self, session: &Session, warn: &WarnSettings) {
+        let duration = session.duration();
+        self.snoozes += session.snoozes;
+
+        if session.pause {
+            self.pause += duration;
+            self.breaks += 1;
+        } else {
+            self.active += duration;
+            self.longest_active = self.longest_active.max(duration);
+            if warn.danger_after != 0 && duration > warn.danger_after {
+                self.danger_exceeded += 1;
+            }
+        }
+    }
+}
+
+/// Totals over the whole history, rolled up per local day, per ISO week and per label.
+///
+/// A session counts towards the day (and week) it started in.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub total: Totals,
+    pub days: BTreeMap<NaiveDate, Totals>,
+    /// Keyed by the monday starting the week.
+    pub weeks: BTreeMap<NaiveDate, Totals>,
+    /// Only labelled sessions.
+    pub labels: BTreeMap<String, Totals>,
+}
+
+impl Stats {
+    pub fn compute(sessions: &[Session], warn: &WarnSettings) -> Self {
+        let mut stats = Self::default();
+
+        for session in sessions {
+            stats.total.add(session, warn);
+            if let Some(label) = &session.label {
+                stats
+                    .labels
+                    .entry(label.clone())
+                    .or_default()
+                    .add(session, warn);
+            }
+
+            let Some(day) = local_date(session.start) else {
+                continue;
+            };
+            stats.days.entry(day).or_default().add(session, warn);
+            stats
+                .weeks
+                .entry(week_start(day))
+                .or_default()
+                .add(session, warn);
+        }
+
+        stats
+    }
+}
+
+/// A unix timestamp in the local timezone.
+pub fn local_time(secs: u64) -> Option<DateTime<Local>> {
+    Local.timestamp_opt(i64::try_from(secs).ok()?, 0).earliest()
+}
+
+/// The local calendar date of a unix timestamp.
+pub fn local_date(secs: u64) -> Option<NaiveDate> {
+    local_time(secs).map(|dt| dt.date_naive())
+}
+
+/// The monday of the ISO week `day` falls in.
+pub fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Days::new(day.weekday().num_days_from_monday() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    /// Unix seconds of a local time in October 2026, so that sessions fall on the same days in
+    /// any timezone.
+    fn local(day: u32, hour: u32, minute: u32) -> u64 {
+        Local
+            .with_ymd_and_hms(2026, 10, day, hour, minute, 0)
+            .unwrap()
+            .timestamp() as u64
+    }
+
+    fn session(pause: bool, start: u64, end: u64) -> Session {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+
+        Session::new(pause, at(start), at(end))
+    }
+
+    /// Friday the 9th and Monday the 12th, the first day going past the danger threshold.
+    fn history() -> Vec<Session> {
+        vec![
+            session(false, local(9, 9, 0), local(9, 10, 10)),
+            session(true, local(9, 10, 10), local(9, 10, 20)),
+            session(false, local(9, 10, 20), local(9, 10, 40)),
+            session(false, local(12, 9, 0), local(12, 9, 50)),
+            session(true, local(12, 9, 50), local(12, 9, 55)),
+        ]
+    }
+
+    /// Active, pause and longest active minutes, danger exceeded and breaks.
+    fn minutes(totals: &Totals) -> (u64, u64, u64, usize, usize) {
+        (
+            totals.active / 60,
+            totals.pause / 60,
+            totals.longest_active / 60,
+            totals.danger_exceeded,
+            totals.breaks,
+        )
+    }
+
+    #[test]
+    fn totals_are_rolled_up_per_day_and_week() {
+        let stats = Stats::compute(&history(), &WarnSettings::from_minutes(30, 60));
+
+        let by_day = |rollup: &BTreeMap<NaiveDate, Totals>| -> Vec<_> {
+            rollup
+                .iter()
+                .map(|(day, totals)| (day.day(), minutes(totals)))
+                .collect()
+        };
+        assert_eq!(minutes(&stats.total), (140, 15, 70, 1, 2));
+        assert_eq!(
+            by_day(&stats.days),
+            [(9, (90, 10, 70, 1, 1)), (12, (50, 5, 50, 0, 1))]
+        );
+        assert_eq!(
+            by_day(&stats.weeks),
+            [(5, (90, 10, 70, 1, 1)), (12, (50, 5, 50, 0, 1))]
+        );
+    }
+
+    #[test]
+    fn longest_active_stretch_is_a_single_session() {
+        let sessions = [
+            session(false, local(9, 9, 0), local(9, 9, 40)),
+            session(false, local(9, 9, 40), local(9, 10, 10)),
+            session(true, local(9, 10, 10), local(9, 10, 15)),
+        ];
+
+        let stats = Stats::compute(&sessions, &WarnSettings::from_minutes(30, 60));
+
+        assert_eq!(stats.total.longest_active, 40 * 60);
+        assert_eq!(stats.total.danger_exceeded, 0);
+    }
+
+    #[test]
+    fn nothing_exceeds_a_disabled_danger_threshold() {
+        let stats = Stats::compute(&history(), &WarnSettings::from_minutes(30, 0));
+
+        assert_eq!(stats.total.danger_exceeded, 0);
+        assert!(stats.days.values().all(|day| day.danger_exceeded == 0));
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        for (day, monday) in [
+            (date(2026, 10, 12), date(2026, 10, 12)),
+            (date(2026, 10, 15), date(2026, 10, 12)),
+            (date(2026, 10, 18), date(2026, 10, 12)),
+            (date(2026, 11, 1), date(2026, 10, 26)),
+            (date(2027, 1, 1), date(2026, 12, 28)),
+        ] {
+            assert_eq!(week_start(day), monday, "week of {}", day);
+        }
+    }
+
+    #[test]
+    fn labelled_sessions_are_totalled_per_label() {
+        let labelled = |label: &str, start, end| Session {
+            label: Some(label.to_owned()),
+            ..session(false, start, end)
+        };
+        let sessions = [
+            labelled("review", local(9, 9, 0), local(9, 9, 30)),
+            session(false, local(9, 9, 30), local(9, 9, 40)),
+            labelled("review", local(12, 9, 0), local(12, 9, 15)),
+            labelled("email", local(12, 9, 15), local(12, 9, 20)),
+        ];
+
+        let stats = Stats::compute(&sessions, &WarnSettings::from_minutes(30, 60));
+
+        let labels: Vec<_> = stats
+            .labels
+            .iter()
+            .map(|(label, totals)| (label.as_str(), totals.active / 60))
+            .collect();
+        assert_eq!(labels, [("email", 5), ("review", 45)]);
+    }
+}
@@ -0,0 +1,353 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::Session;
+
+/// The stopwatch engine, alternating between active and paused stretches.
+///
+/// Every toggle closes the current stretch into a [`Session`]. All methods taking a `now` allow
+/// driving the engine with a fixed clock.
+#[derive(Debug, Clone)]
+pub struct Stopwatch {
+    paused: bool,
+    start: SystemTime,
+    snoozes: u32,
+    label: Option<String>,
+    sessions: Vec<Session>,
+}
+
+impl Default for Stopwatch {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl Stopwatch {
+    pub fn new(paused: bool) -> Self {
+        Self::new_at(paused, SystemTime::now())
+    }
+
+    pub fn new_at(paused: bool, now: SystemTime) -> Self {
+        Self {
+            paused,
+            start: now,
+            snoozes: 0,
+            label: None,
+            sessions: vec![],
+        }
+    }
+
+    /// Continues where a previous run left off.
+    ///
+    /// Only the last run is taken from `history`: the trailing sessions that each start where
+    /// the previous one ended, up to the last reset. The current stretch is the opposite of the
+    /// last session and has been going on since it ended, labelled like the last active session.
+    /// After a reset it is a fresh pause instead. Returns `None` for an empty history.
+    pub fn resume(history: &[Session]) -> Option<Self> {
+        let last = history.last()?;
+        if last.reset {
+            return Some(Self::new_at(
+                true,
+                UNIX_EPOCH + Duration::from_secs(last.end),
+            ));
+        }
+
+        let run_start = history
+            .windows(2)
+            .rposition(|pair| pair[0].end != pair[1].start || pair[0].reset)
+            .map_or(0, |i| i + 1);
+
+        Some(Self {
+            paused: !last.pause,
+            start: UNIX_EPOCH + Duration::from_secs(last.end),
+            snoozes: 0,
+            label: history
+                .iter()
+                .rev()
+                .find(|s| !s.pause)
+                .and_then(|s| s.label.clone()),
+            sessions: history[run_start..].to_vec(),
+        })
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// When the current (active or paused) stretch began.
+    pub fn start(&self) -> SystemTime {
+        self.start
+    }
+
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    pub fn breaks(&self) -> usize {
+        self.sessions.iter().filter(|s| s.pause).count()
+    }
+
+    /// Seconds spent in the current stretch.
+    pub fn elapsed(&self) -> u64 {
+        self.elapsed_at(SystemTime::now())
+    }
+
+    pub fn elapsed_at(&self, now: SystemTime) -> u64 {
+        now.duration_since(self.start)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+
+    /// The activity active stretches are labelled with.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Labels the current stretch if active, and every active stretch after it.
+    pub fn set_label(&mut self, label: Option<String>) {
+        self.label = label;
+    }
+
+    /// Records that a break reminder was postponed during the current stretch.
+    pub fn snooze(&mut self) {
+        self.snoozes += 1;
+    }
+
+    /// The current stretch as a session ending now.
+    pub fn current(&self) -> Session {
+        self.session_until(SystemTime::now())
+    }
+
+    /// Moves the start of the current stretch back by `by`, so that after the wall clock was set
+    /// back the elapsed time carries on instead of starting over.
+    pub fn rewind_start(&mut self, by: Duration) {
+        if let Some(start) = self.start.checked_sub(by) {
+            self.start = start;
+        }
+    }
+
+    /// Turns the part of the active stretch between `start` and `end` into a break, as if the
+    /// stopwatch had been paused in between.
+    ///
+    /// Returns the active session up to `start` and the break, none if paused or the stretch
+    /// began after `end`.
+    pub fn pause_between(&mut self, start: SystemTime, end: SystemTime) -> Vec<Session> {
+        if self.paused || end <= self.start {
+            return vec![];
+        }
+
+        let start = start.max(self.start);
+        vec![
+            self.toggle_automatic_at(start),
+            self.toggle_automatic_at(end),
+        ]
+    }
+
+    /// Ends the current stretch and the run with it, starting over paused with no breaks.
+    ///
+    /// Returns the finished session, marked so that [`Stopwatch::resume`] starts after it.
+    pub fn reset_at(&mut self, now: SystemTime) -> Session {
+        let session = Session {
+            reset: true,
+            ..self.session_until(now)
+        };
+        *self = Self::new_at(true, now);
+
+        session
+    }
+
+    /// Takes back the last toggle, carrying on with the stretch it ended as if it never had.
+    ///
+    /// Returns the session that was taken back, `None` if there was no toggle this run.
+    pub fn undo_toggle(&mut self) -> Option<Session> {
+        let session = self.sessions.pop()?;
+        self.paused = session.pause;
+        self.start = UNIX_EPOCH + Duration::from_secs(session.start);
+        self.snoozes = session.snoozes;
+        if session.label.is_some() {
+            self.label = session.label.clone();
+        }
+
+        Some(session)
+    }
+
+    /// Like [`Stopwatch::toggle_at`], but marks the session as ended automatically.
+    pub fn toggle_automatic_at(&mut self, now: SystemTime) -> Session {
+        self.toggle_with(now, true)
+    }
+
+    fn session_until(&self, end: SystemTime) -> Session {
+        Session {
+            snoozes: self.snoozes,
+            label: if self.paused {
+                None
+            } else {
+                self.label.clone()
+            },
+            ..Session::new(self.paused, self.start, end)
+        }
+    }
+
+    /// Ends the current stretch and starts the opposite one, returning the finished session.
+    pub fn toggle(&mut self) -> Session {
+        self.toggle_at(SystemTime::now())
+    }
+
+    pub fn toggle_at(&mut self, now: SystemTime) -> Session {
+        self.toggle_with(now, false)
+    }
+
+    fn toggle_with(&mut self, now: SystemTime, automatic: bool) -> Session {
+        let session = Session {
+            automatic,
+            ..self.session_until(now)
+        };
+        self.sessions.push(session.clone());
+        self.start = now;
+        self.snoozes = 0;
+        self.paused = !self.paused;
+
+        session
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn session(pause: bool, start: u64, end: u64) -> Session {
+        Session::new(pause, at(start), at(end))
+    }
+
+    #[test]
+    fn toggling_alternates_and_records_sessions() {
+        let mut stopwatch = Stopwatch::new_at(true, at(0));
+        stopwatch.set_label(Some("review".to_owned()));
+
+        let pause = stopwatch.toggle_at(at(10));
+        stopwatch.snooze();
+        let active = stopwatch.toggle_at(at(25));
+
+        assert_eq!(pause, session(true, 0, 10));
+        assert_eq!(active.label.as_deref(), Some("review"));
+        assert_eq!(active.snoozes, 1);
+        assert_eq!((active.start, active.end), (10, 25));
+        assert!(stopwatch.is_paused());
+        assert_eq!(stopwatch.start(), at(25));
+        assert_eq!(stopwatch.elapsed_at(at(30)), 5);
+        assert_eq!(stopwatch.breaks(), 1);
+        assert_eq!(stopwatch.sessions().len(), 2);
+    }
+
+    #[test]
+    fn elapsed_is_zero_before_the_start() {
+        let stopwatch = Stopwatch::new_at(false, at(100));
+
+        assert_eq!(stopwatch.elapsed_at(at(50)), 0);
+    }
+
+    #[test]
+    fn resume_continues_the_last_run_only() {
+        let labelled = Session {
+            label: Some("review".to_owned()),
+            ..session(false, 100, 110)
+        };
+        let history = [
+            session(false, 0, 10),
+            session(true, 10, 20),
+            labelled.clone(),
+            session(true, 110, 120),
+        ];
+
+        let stopwatch = Stopwatch::resume(&history).unwrap();
+
+        assert!(!stopwatch.is_paused());
+        assert_eq!(stopwatch.start(), at(120));
+        assert_eq!(stopwatch.label(), Some("review"));
+        assert_eq!(stopwatch.sessions(), [labelled, session(true, 110, 120)]);
+        assert!(Stopwatch::resume(&[]).is_none());
+    }
+
+    #[test]
+    fn resume_starts_after_a_reset() {
+        let reset = Session {
+            reset: true,
+            ..session(false, 10, 20)
+        };
+
+        let stopwatch = Stopwatch::resume(&[session(true, 0, 10), reset.clone()]).unwrap();
+        assert!(stopwatch.is_paused());
+        assert_eq!(stopwatch.start(), at(20));
+        assert!(stopwatch.sessions().is_empty());
+
+        let history = [session(true, 0, 10), reset, session(true, 20, 30)];
+        let stopwatch = Stopwatch::resume(&history).unwrap();
+        assert!(!stopwatch.is_paused());
+        assert_eq!(stopwatch.sessions(), [session(true, 20, 30)]);
+    }
+
+    #[test]
+    fn reset_ends_the_run() {
+        let mut stopwatch = Stopwatch::new_at(false, at(0));
+        stopwatch.toggle_at(at(10));
+        stopwatch.toggle_at(at(20));
+
+        let last = stopwatch.reset_at(at(30));
+
+        assert!(last.reset && !last.pause);
+        assert_eq!((last.start, last.end), (20, 30));
+        assert!(stopwatch.is_paused());
+        assert_eq!(stopwatch.start(), at(30));
+        assert_eq!(stopwatch.breaks(), 0);
+    }
+
+    #[test]
+    fn undo_restores_the_stretch_before_the_toggle() {
+        let mut stopwatch = Stopwatch::new_at(false, at(0));
+        stopwatch.set_label(Some("review".to_owned()));
+        stopwatch.snooze();
+        let toggled = stopwatch.toggle_at(at(10));
+        stopwatch.set_label(None);
+
+        assert_eq!(stopwatch.undo_toggle(), Some(toggled));
+        assert!(!stopwatch.is_paused());
+        assert_eq!(stopwatch.start(), at(0));
+        assert_eq!(stopwatch.label(), Some("review"));
+        assert_eq!(stopwatch.current().snoozes, 1);
+        assert!(stopwatch.sessions().is_empty());
+        assert_eq!(stopwatch.undo_toggle(), None);
+    }
+
+    #[test]
+    fn pause_between_splits_the_active_stretch() {
+        let mut stopwatch = Stopwatch::new_at(false, at(100));
+
+        let sessions = stopwatch.pause_between(at(50), at(200));
+
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(
+            (sessions[0].pause, sessions[0].start, sessions[0].end),
+            (false, 100, 100)
+        );
+        assert_eq!(
+            (sessions[1].pause, sessions[1].start, sessions[1].end),
+            (true, 100, 200)
+        );
+        assert!(sessions.iter().all(|s| s.automatic));
+        assert!(!stopwatch.is_paused());
+        assert_eq!(stopwatch.start(), at(200));
+    }
+
+    #[test]
+    fn pause_between_leaves_paused_or_later_stretches_alone() {
+        let mut paused = Stopwatch::new_at(true, at(0));
+        assert!(paused.pause_between(at(10), at(20)).is_empty());
+
+        let mut later = Stopwatch::new_at(false, at(300));
+        assert!(later.pause_between(at(10), at(20)).is_empty());
+        assert_eq!(later.start(), at(300));
+    }
+}
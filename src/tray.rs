@@ -0,0 +1,289 @@
+use crate::{format_text, Level};
+
+/// What the tray icon shows about the selected stopwatch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrayStatus {
+    pub paused: bool,
+    pub level: Level,
+    /// Seconds in the current stretch.
+    pub elapsed: u64,
+    pub label: Option<String>,
+}
+
+impl TrayStatus {
+    pub fn tooltip(&self) -> String {
+        let state = if self.paused { "paused" } else { "active" };
+        let label = self
+            .label
+            .as_ref()
+            .filter(|_| !self.paused)
+            .map(|label| format!(" ({})", label))
+            .unwrap_or_default();
+
+        format!("{} {}{}", state, format_text(self.elapsed, false), label)
+    }
+}
+
+/// Entries of the tray menu, and clicking the icon itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayAction {
+    /// Brings the window back, hidden or not.
+    Show,
+    Toggle,
+    Reset,
+    Stats,
+    Settings,
+    Quit,
+}
+
+/// An icon in the system tray, mirroring the selected stopwatch.
+pub trait TrayIcon: std::fmt::Debug {
+    fn update(&mut self, status: &TrayStatus);
+
+    /// Actions picked since the last call.
+    fn poll(&mut self) -> Vec<TrayAction>;
+}
+
+/// A StatusNotifierItem, shown by most Linux desktops.
+#[cfg(all(feature = "tray", target_os = "linux"))]
+pub struct SniTray {
+    handle: ksni::Handle<SniItem>,
+    status: Option<TrayStatus>,
+    actions: std::sync::mpsc::Receiver<TrayAction>,
+}
+
+#[cfg(all(feature = "tray", target_os = "linux"))]
+impl std::fmt::Debug for SniTray {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SniTray")
+            .field("status", &self.status)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(feature = "tray", target_os = "linux"))]
+impl SniTray {
+    /// Registers the icon with the session bus, the tray showing up once the desktop picks it up.
+    pub fn spawn() -> Result<Self, String> {
+        let icons = [Level::Off, Level::Ok, Level::Warn, Level::Danger]
+            .into_iter()
+            .map(tinted_icon)
+            .collect::<Result<_, _>>()?;
+        let (sender, actions) = std::sync::mpsc::channel();
+
+        let service = ksni::TrayService::new(SniItem {
+            status: None,
+            icons,
+            sender,
+        });
+        let handle = service.handle();
+        service.spawn();
+
+        Ok(Self {
+            handle,
+            status: None,
+            actions,
+        })
+    }
+}
+
+#[cfg(all(feature = "tray", target_os = "linux"))]
+impl TrayIcon for SniTray {
+    fn update(&mut self, status: &TrayStatus) {
+        // Every update is a D-Bus signal, so only send one when something visible changed.
+        if self.status.as_ref() == Some(status) {
+            return;
+        }
+
+        self.status = Some(status.clone());
+        let status = status.clone();
+        self.handle.update(move |item| item.status = Some(status));
+    }
+
+    fn poll(&mut self) -> Vec<TrayAction> {
+        self.actions.try_iter().collect()
+    }
+}
+
+#[cfg(all(feature = "tray", target_os = "linux"))]
+struct SniItem {
+    status: Option<TrayStatus>,
+    /// One icon per [`Level`], in the order of [`icon_index`].
+    icons: Vec<ksni::Icon>,
+    sender: std::sync::mpsc::Sender<TrayAction>,
+}
+
+#[cfg(all(feature = "tray", target_os = "linux"))]
+impl SniItem {
+    fn send(&self, action: TrayAction) {
+        if let Err(e) = self.sender.send(action) {
+            eprintln!("Failed to pass on tray action: {}", e);
+        }
+    }
+
+    fn entry(label: &str, action: TrayAction) -> ksni::MenuItem<Self> {
+        ksni::menu::StandardItem {
+            label: label.to_owned(),
+            activate: Box::new(move |item: &mut Self| item.send(action)),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(all(feature = "tray", target_os = "linux"))]
+impl ksni::Tray for SniItem {
+    fn id(&self) -> String {
+        "zarthus_stopwatch".to_owned()
+    }
+
+    fn title(&self) -> String {
+        "Stopwatch".to_owned()
+    }
+
+    fn icon_pixmap(&self) -> Vec<ksni::Icon> {
+        let level = self.status.as_ref().map_or(Level::Off, |status| {
+            if status.paused {
+                Level::Off
+            } else {
+                status.level
+            }
+        });
+
+        vec![self.icons[icon_index(level)].clone()]
+    }
+
+    fn tool_tip(&self) -> ksni::ToolTip {
+        ksni::ToolTip {
+            title: "Stopwatch".to_owned(),
+            description: self
+                .status
+                .as_ref()
+                .map(TrayStatus::tooltip)
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    fn activate(&mut self, _x: i32, _y: i32) {
+        self.send(TrayAction::Show);
+    }
+
+    fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+        let toggle = match &self.status {
+            Some(status) if !status.paused => "Pause",
+            _ => "Start",
+        };
+
+        vec![
+            Self::entry(toggle, TrayAction::Toggle),
+            Self::entry("Reset", TrayAction::Reset),
+            ksni::MenuItem::Separator,
+            Self::entry("Show", TrayAction::Show),
+            Self::entry("Stats", TrayAction::Stats),
+            Self::entry("Settings", TrayAction::Settings),
+            ksni::MenuItem::Separator,
+            Self::entry("Quit", TrayAction::Quit),
+        ]
+    }
+}
+
+#[cfg(all(feature = "tray", target_os = "linux"))]
+fn icon_index(level: Level) -> usize {
+    match level {
+        Level::Off => 0,
+        Level::Ok => 1,
+        Level::Warn => 2,
+        Level::Danger => 3,
+    }
+}
+
+/// The app icon tinted in the colour of `level`, paused being left as is.
+#[cfg(all(feature = "tray", target_os = "linux"))]
+fn tinted_icon(level: Level) -> Result<ksni::Icon, String> {
+    let image = image::load_from_memory(include_bytes!("../resource/icon.png"))
+        .map_err(|e| format!("Failed to load tray icon: {}", e))?
+        .into_rgba8();
+    let tint: Option<[u8; 3]> = match level {
+        Level::Off => None,
+        Level::Ok => Some([0, 255, 0]),
+        Level::Warn => Some([255, 255, 0]),
+        Level::Danger => Some([255, 0, 0]),
+    };
+
+    // StatusNotifierItem wants ARGB32 in network byte order.
+    let mut data = Vec::with_capacity(image.as_raw().len());
+    for pixel in image.pixels() {
+        let [r, g, b, a] = pixel.0;
+        let [r, g, b] = match tint {
+            Some([tr, tg, tb]) => {
+                let grey = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+                [
+                    tint_channel(grey, tr),
+                    tint_channel(grey, tg),
+                    tint_channel(grey, tb),
+                ]
+            }
+            None => [r, g, b],
+        };
+        data.extend_from_slice(&[a, r, g, b]);
+    }
+
+    Ok(ksni::Icon {
+        width: image.width() as i32,
+        height: image.height() as i32,
+        data,
+    })
+}
+
+#[cfg(all(feature = "tray", target_os = "linux"))]
+fn tint_channel(grey: u8, tint: u8) -> u8 {
+    (grey as u16 * tint as u16 / 255) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(paused: bool) -> TrayStatus {
+        TrayStatus {
+            paused,
+            level: Level::Ok,
+            elapsed: 754,
+            label: Some("review".to_owned()),
+        }
+    }
+
+    #[test]
+    fn tooltip_shows_the_label_while_active() {
+        assert_eq!(status(false).tooltip(), "active 12:34 (review)");
+        assert_eq!(status(true).tooltip(), "paused 12:34");
+    }
+
+    #[cfg(all(feature = "tray", target_os = "linux"))]
+    #[test]
+    fn every_level_has_an_icon_of_its_own() {
+        let levels = [Level::Off, Level::Ok, Level::Warn, Level::Danger];
+        let indices: Vec<usize> = levels.into_iter().map(icon_index).collect();
+
+        assert_eq!(indices, [0, 1, 2, 3]);
+    }
+
+    #[cfg(all(feature = "tray", target_os = "linux"))]
+    #[test]
+    fn icons_are_tinted_in_the_colour_of_the_level() {
+        let image = image::load_from_memory(include_bytes!("../resource/icon.png"))
+            .unwrap()
+            .into_rgba8();
+        let off = tinted_icon(Level::Off).unwrap();
+        let danger = tinted_icon(Level::Danger).unwrap();
+
+        assert_eq!((off.width as u32, off.height as u32), image.dimensions());
+        let [r, g, b, a] = image.get_pixel(0, 0).0;
+        assert_eq!(off.data[..4], [a, r, g, b]);
+        for (plain, red) in off.data.chunks(4).zip(danger.data.chunks(4)) {
+            assert_eq!(red[0], plain[0]);
+            assert_eq!((red[2], red[3]), (0, 0));
+        }
+    }
+}
@@ -0,0 +1,41 @@
+/// Thresholds after which an active session should be highlighted, in seconds.
+#[derive(Debug, Clone, Copy)]
+pub struct WarnSettings {
+    pub warn_after: u64,
+    pub danger_after: u64,
+}
+
+/// How urgently a break is due, ordered from least to most urgent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Both thresholds are disabled.
+    Off,
+    Ok,
+    Warn,
+    Danger,
+}
+
+impl WarnSettings {
+    pub fn from_minutes(warn_after_minutes: u16, danger_after_minutes: u16) -> Self {
+        Self {
+            warn_after: warn_after_minutes as u64 * 60,
+            danger_after: danger_after_minutes as u64 * 60,
+        }
+    }
+
+    pub fn level(&self, seconds: u64) -> Level {
+        if self.danger_after == 0 && self.warn_after == 0 {
+            return Level::Off;
+        }
+
+        if seconds > self.danger_after {
+            return Level::Danger;
+        }
+
+        if seconds > self.warn_after {
+            return Level::Warn;
+        }
+
+        Level::Ok
+    }
+}